@@ -0,0 +1,90 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct Flat {
+    keyword: String,
+    page: u32,
+    per_page: u32,
+    sort: String,
+    lang: Option<String>,
+    safe: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct Nested {
+    keyword: String,
+    page: Page,
+    filter: Filter,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct Page {
+    number: u32,
+    size: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct Filter {
+    category: String,
+    price: Price,
+    brand: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct Price {
+    min: u32,
+    max: u32,
+}
+
+const FLAT: &str =
+    "keyword=rust+serde+%E3%82%AF%E3%82%A8%E3%83%AA&page=3&per_page=50&sort=relevance&lang=ja&safe=true";
+
+const NESTED: &str = "keyword=rust+serde&page.number=3&page.size=50&filter.category=books\
+    &filter.price.min=1000&filter.price.max=5000&filter.brand=O%27Reilly";
+
+/// The same query as [`NESTED`] in the bracket notation of serde_qs.
+const NESTED_BRACKETS: &str =
+    "keyword=rust+serde&page[number]=3&page[size]=50&filter[category]=books\
+    &filter[price][min]=1000&filter[price][max]=5000&filter[brand]=O%27Reilly";
+
+fn flat(c: &mut Criterion) {
+    let mut group = c.benchmark_group("flat");
+    group.bench_with_input(
+        BenchmarkId::new("serde_structuredqs", "flat"),
+        FLAT,
+        |b, input| b.iter(|| serde_structuredqs::from_str::<Flat>(input).unwrap()),
+    );
+    group.bench_with_input(BenchmarkId::new("serde_qs", "flat"), FLAT, |b, input| {
+        b.iter(|| serde_qs::from_str::<Flat>(input).unwrap())
+    });
+    group.bench_with_input(
+        BenchmarkId::new("serde_urlencoded", "flat"),
+        FLAT,
+        |b, input| b.iter(|| serde_urlencoded::from_str::<Flat>(input).unwrap()),
+    );
+    group.finish();
+}
+
+fn nested(c: &mut Criterion) {
+    let mut group = c.benchmark_group("nested");
+    group.bench_with_input(
+        BenchmarkId::new("serde_structuredqs", "nested"),
+        NESTED,
+        |b, input| b.iter(|| serde_structuredqs::from_str::<Nested>(input).unwrap()),
+    );
+    group.bench_with_input(
+        BenchmarkId::new("serde_qs", "nested"),
+        NESTED_BRACKETS,
+        |b, input| b.iter(|| serde_qs::from_str::<Nested>(input).unwrap()),
+    );
+    group.finish();
+}
+
+criterion_group!(benches, flat, nested);
+criterion_main!(benches);
@@ -0,0 +1,150 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Flat {
+    keyword: String,
+    page: u32,
+    per_page: u32,
+    sort: String,
+    lang: Option<String>,
+    safe: bool,
+}
+
+#[derive(Serialize)]
+struct Nested {
+    keyword: String,
+    page: Page,
+    filter: Filter,
+}
+
+#[derive(Serialize)]
+struct Page {
+    number: u32,
+    size: u32,
+}
+
+#[derive(Serialize)]
+struct Filter {
+    category: String,
+    price: Price,
+    brand: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Price {
+    min: u32,
+    max: u32,
+}
+
+#[derive(Serialize)]
+struct Sequences {
+    ids: Vec<u64>,
+    tags: Vec<String>,
+    ranges: Vec<(u32, u32)>,
+}
+
+fn flat() -> Flat {
+    Flat {
+        keyword: String::from("rust serde クエリ"),
+        page: 3,
+        per_page: 50,
+        sort: String::from("relevance"),
+        lang: Some(String::from("ja")),
+        safe: true,
+    }
+}
+
+fn nested(number: u32) -> Nested {
+    Nested {
+        keyword: String::from("rust serde"),
+        page: Page { number, size: 50 },
+        filter: Filter {
+            category: String::from("books"),
+            price: Price {
+                min: 1000,
+                max: 5000,
+            },
+            brand: Some(String::from("O'Reilly")),
+        },
+    }
+}
+
+fn sequences() -> Sequences {
+    Sequences {
+        ids: (1_000_000..1_000_100).collect(),
+        tags: ["rust", "serde", "query,string", "url"]
+            .iter()
+            .map(|tag| tag.to_string())
+            .collect(),
+        ranges: vec![(0, 100), (200, 300), (400, 500)],
+    }
+}
+
+fn serialize_flat(c: &mut Criterion) {
+    let value = flat();
+    let mut group = c.benchmark_group("serialize_flat");
+    group.bench_with_input(
+        BenchmarkId::new("serde_structuredqs", "flat"),
+        &value,
+        |b, value| b.iter(|| serde_structuredqs::to_string(value).unwrap()),
+    );
+    group.bench_with_input(BenchmarkId::new("serde_qs", "flat"), &value, |b, value| {
+        b.iter(|| serde_qs::to_string(value).unwrap())
+    });
+    group.bench_with_input(
+        BenchmarkId::new("serde_urlencoded", "flat"),
+        &value,
+        |b, value| b.iter(|| serde_urlencoded::to_string(value).unwrap()),
+    );
+    group.finish();
+}
+
+fn serialize_nested(c: &mut Criterion) {
+    let value = nested(3);
+    let mut group = c.benchmark_group("serialize_nested");
+    group.bench_with_input(
+        BenchmarkId::new("serde_structuredqs", "nested"),
+        &value,
+        |b, value| b.iter(|| serde_structuredqs::to_string(value).unwrap()),
+    );
+    group.bench_with_input(
+        BenchmarkId::new("serde_qs", "nested"),
+        &value,
+        |b, value| b.iter(|| serde_qs::to_string(value).unwrap()),
+    );
+    group.finish();
+}
+
+fn serialize_sequences(c: &mut Criterion) {
+    let value = sequences();
+    let mut group = c.benchmark_group("serialize_sequences");
+    group.bench_with_input(
+        BenchmarkId::new("serde_structuredqs", "sequences"),
+        &value,
+        |b, value| b.iter(|| serde_structuredqs::to_string(value).unwrap()),
+    );
+    group.finish();
+}
+
+/// Pagination links of a result page, one query per page.
+fn serialize_pagination(c: &mut Criterion) {
+    let pages: Vec<Nested> = (1..=1000).map(nested).collect();
+    c.bench_function("serialize_pagination/serde_structuredqs", |b| {
+        b.iter(|| {
+            pages
+                .iter()
+                .map(|page| serde_structuredqs::to_string(page).unwrap())
+                .collect::<Vec<_>>()
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    serialize_flat,
+    serialize_nested,
+    serialize_sequences,
+    serialize_pagination
+);
+criterion_main!(benches);
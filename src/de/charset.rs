@@ -0,0 +1,82 @@
+use std::borrow::Cow;
+
+/// Character encoding of the percent-decoded bytes of keys and values.
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde_structuredqs::{Charset, DeserializerConfig};
+///
+/// #[derive(Debug, Deserialize, Eq, PartialEq)]
+/// struct Params {
+///     q: String,
+/// }
+///
+/// let config = DeserializerConfig::new().charset(Charset::Utf8Lossy);
+/// assert_eq!(
+///     config.deserialize_str::<Params>("q=caf%E9").unwrap(),
+///     Params { q: String::from("caf\u{FFFD}") }
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Charset {
+    /// UTF-8. Invalid sequences fail with [`Error::Parse`](crate::Error::Parse).
+    #[default]
+    Utf8,
+    /// UTF-8, with invalid sequences replaced by `U+FFFD REPLACEMENT CHARACTER`.
+    Utf8Lossy,
+    /// Any encoding of the [Encoding Standard](https://encoding.spec.whatwg.org/),
+    /// such as `Shift_JIS`, `EUC-JP` or `windows-1252` (which browsers use for `ISO-8859-1`).
+    /// Invalid sequences fail with [`Error::Parse`](crate::Error::Parse).
+    #[cfg(feature = "charset")]
+    Encoding(&'static encoding_rs::Encoding),
+}
+
+impl Charset {
+    /// Look up a charset by a label such as `Shift_JIS` or `latin1`, as in the `_charset_` parameter.
+    #[cfg(feature = "charset")]
+    pub fn for_label(label: &[u8]) -> Option<Self> {
+        encoding_rs::Encoding::for_label(label).map(|encoding| {
+            if encoding == encoding_rs::UTF_8 {
+                Charset::Utf8
+            } else {
+                Charset::Encoding(encoding)
+            }
+        })
+    }
+
+    /// Decode percent-decoded bytes into a string, borrowing them when possible.
+    pub(crate) fn decode(self, bytes: Cow<'_, [u8]>) -> Result<Cow<'_, str>, String> {
+        match self {
+            Charset::Utf8 => match bytes {
+                Cow::Borrowed(bytes) => std::str::from_utf8(bytes)
+                    .map(Cow::Borrowed)
+                    .map_err(|e| e.to_string()),
+                Cow::Owned(bytes) => String::from_utf8(bytes)
+                    .map(Cow::Owned)
+                    .map_err(|e| e.to_string()),
+            },
+            Charset::Utf8Lossy => match bytes {
+                Cow::Borrowed(bytes) => Ok(String::from_utf8_lossy(bytes)),
+                Cow::Owned(bytes) => {
+                    Ok(Cow::Owned(String::from_utf8(bytes).unwrap_or_else(|e| {
+                        String::from_utf8_lossy(e.as_bytes()).into_owned()
+                    })))
+                }
+            },
+            #[cfg(feature = "charset")]
+            Charset::Encoding(encoding) => {
+                let invalid = || format!("invalid {} sequence", encoding.name());
+                match bytes {
+                    Cow::Borrowed(bytes) => encoding
+                        .decode_without_bom_handling_and_without_replacement(bytes)
+                        .ok_or_else(invalid),
+                    Cow::Owned(bytes) => encoding
+                        .decode_without_bom_handling_and_without_replacement(&bytes)
+                        .map(|decoded| Cow::Owned(decoded.into_owned()))
+                        .ok_or_else(invalid),
+                }
+            }
+        }
+    }
+}
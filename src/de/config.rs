@@ -0,0 +1,401 @@
+use crate::{
+    de::{
+        charset::Charset,
+        incremental::IncrementalParser,
+        key::KeyMatching,
+        level::LevelDeserializer,
+        parsed::ParsedQuery,
+        parser::{self, Parser},
+    },
+    error::Result,
+    sequence::Separators,
+};
+
+use serde::de;
+use std::io;
+
+/// Options for deserializing query-strings.
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde_structuredqs::{DeserializerConfig, Separators};
+///
+/// #[derive(Debug, Deserialize, Eq, PartialEq)]
+/// struct Polygon {
+///     points: Vec<(i32, i32)>,
+/// }
+///
+/// let config = DeserializerConfig::new().separators(Separators::new(&['~', '!']).unwrap());
+/// assert_eq!(
+///     config.deserialize_str::<Polygon>("points=0~0!3~0!0~4").unwrap(),
+///     Polygon { points: vec![(0, 0), (3, 0), (0, 4)] }
+/// );
+/// ```
+///
+/// The default configuration limits keys to 32 nested segments and sets no other limit.
+/// When parsing untrusted input, set the limits that fit your inputs;
+/// exceeding any of them fails with [`Error::LimitExceeded`](crate::Error::LimitExceeded)
+/// before the offending part is decoded.
+#[derive(Clone, Copy, Debug)]
+pub struct DeserializerConfig {
+    pub(crate) separators: Separators,
+    pub(crate) key_matching: KeyMatching,
+    pub(crate) max_depth: usize,
+    pub(crate) max_pairs: usize,
+    pub(crate) max_key_len: usize,
+    pub(crate) max_value_len: usize,
+    pub(crate) max_sequence_len: usize,
+    pub(crate) max_input_len: usize,
+    pub(crate) strict: bool,
+    pub(crate) plus_as_space: bool,
+    pub(crate) charset: Charset,
+    #[cfg(feature = "charset")]
+    pub(crate) charset_parameter: bool,
+}
+
+impl Default for DeserializerConfig {
+    fn default() -> Self {
+        Self {
+            separators: Separators::default(),
+            key_matching: KeyMatching::default(),
+            max_depth: 32,
+            max_pairs: usize::MAX,
+            max_key_len: usize::MAX,
+            max_value_len: usize::MAX,
+            max_sequence_len: usize::MAX,
+            max_input_len: usize::MAX,
+            strict: false,
+            plus_as_space: true,
+            charset: Charset::default(),
+            #[cfg(feature = "charset")]
+            charset_parameter: false,
+        }
+    }
+}
+
+impl DeserializerConfig {
+    /// Returns the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of `.`-separated segments in a key.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Set the maximum number of `key=value` pairs.
+    pub fn max_pairs(mut self, max_pairs: usize) -> Self {
+        self.max_pairs = max_pairs;
+        self
+    }
+
+    /// Set the maximum length of a key in bytes, before percent-decoding.
+    pub fn max_key_len(mut self, max_key_len: usize) -> Self {
+        self.max_key_len = max_key_len;
+        self
+    }
+
+    /// Set the maximum length of a value in bytes, before percent-decoding.
+    pub fn max_value_len(mut self, max_value_len: usize) -> Self {
+        self.max_value_len = max_value_len;
+        self
+    }
+
+    /// Set the maximum number of elements in a sequence value.
+    pub fn max_sequence_len(mut self, max_sequence_len: usize) -> Self {
+        self.max_sequence_len = max_sequence_len;
+        self
+    }
+
+    /// Set the maximum length of the whole input in bytes.
+    pub fn max_input_len(mut self, max_input_len: usize) -> Self {
+        self.max_input_len = max_input_len;
+        self
+    }
+
+    /// Set the hierarchy of separators used to split nested sequences.
+    pub fn separators(mut self, separators: Separators) -> Self {
+        self.separators = separators;
+        self
+    }
+
+    /// Set how keys are matched against the fields of structs.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_structuredqs::{DeserializerConfig, KeyMatching};
+    ///
+    /// #[derive(Debug, Deserialize, Eq, PartialEq)]
+    /// struct Params {
+    ///     keyword: String,
+    ///     page_size: u32,
+    /// }
+    ///
+    /// let config = DeserializerConfig::new().key_matching(KeyMatching::StyleInsensitive);
+    /// assert_eq!(
+    ///     config.deserialize_str::<Params>("Keyword=foo&pageSize=20").unwrap(),
+    ///     Params { keyword: String::from("foo"), page_size: 20 }
+    /// );
+    /// ```
+    pub fn key_matching(mut self, key_matching: KeyMatching) -> Self {
+        self.key_matching = key_matching;
+        self
+    }
+
+    /// Enable or disable strict syntax validation.
+    ///
+    /// By default, the parser forgives empty pairs (`a=1&&b=2`), empty keys (`=1`),
+    /// empty key segments (`.a`, `a.`, `a..b`), a `=` inside a value (`a==1`)
+    /// and malformed percent-encoding (`%zz`), as browsers may send them.
+    /// In strict mode, each of them fails with [`Error::Parse`](crate::Error::Parse)
+    /// pointing at its byte offset.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_structuredqs::{DeserializerConfig, Error};
+    ///
+    /// #[derive(Debug, Deserialize)]
+    /// struct Params {
+    ///     a: Option<String>,
+    ///     b: Option<String>,
+    /// }
+    ///
+    /// let config = DeserializerConfig::new().strict(true);
+    /// assert!(config.deserialize_str::<Params>("a=1&b=2").is_ok());
+    /// assert!(matches!(
+    ///     config.deserialize_str::<Params>("a=1&&b=2"),
+    ///     Err(Error::Parse(_, 4))
+    /// ));
+    /// ```
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Set whether `+` is decoded as a space, as in HTML forms, or kept as a literal `+`, as in RFC 3986.
+    ///
+    /// It is decoded as a space by default. `%20` is decoded as a space in both cases.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_structuredqs::DeserializerConfig;
+    ///
+    /// #[derive(Debug, Deserialize, Eq, PartialEq)]
+    /// struct Params<'a> {
+    ///     tz: &'a str,
+    ///     q: String,
+    /// }
+    ///
+    /// let config = DeserializerConfig::new().plus_as_space(false);
+    /// assert_eq!(
+    ///     config.deserialize_str::<Params>("tz=+09:00&q=c++%20code").unwrap(),
+    ///     Params { tz: "+09:00", q: String::from("c++ code") }
+    /// );
+    /// ```
+    pub fn plus_as_space(mut self, plus_as_space: bool) -> Self {
+        self.plus_as_space = plus_as_space;
+        self
+    }
+
+    /// Set the character encoding of the percent-decoded keys and values. It is UTF-8 by default.
+    pub fn charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    /// Set whether the `_charset_` parameter sent by HTML forms overrides the configured charset.
+    ///
+    /// The parameter itself is kept and deserialized like any other pair.
+    /// An unknown label fails with [`Error::Parse`](crate::Error::Parse).
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_structuredqs::DeserializerConfig;
+    ///
+    /// #[derive(Debug, Deserialize, Eq, PartialEq)]
+    /// struct Params {
+    ///     name: String,
+    /// }
+    ///
+    /// let config = DeserializerConfig::new().charset_parameter(true);
+    /// assert_eq!(
+    ///     config.deserialize_str::<Params>("_charset_=Shift_JIS&name=%83n%83i%83R").unwrap(),
+    ///     Params { name: String::from("ハナコ") }
+    /// );
+    /// ```
+    #[cfg(feature = "charset")]
+    pub fn charset_parameter(mut self, charset_parameter: bool) -> Self {
+        self.charset_parameter = charset_parameter;
+        self
+    }
+
+    /// Parse query-string from a `&[u8]` with this configuration, to deserialize it later.
+    pub fn parse_bytes<'de>(&self, input: &'de [u8]) -> Result<ParsedQuery<'de>> {
+        Parser::new(input, *self).parse().map(ParsedQuery::new)
+    }
+
+    /// Parse query-string from a `&str` with this configuration, to deserialize it later.
+    pub fn parse_str<'de>(&self, input: &'de str) -> Result<ParsedQuery<'de>> {
+        self.parse_bytes(input.as_bytes())
+    }
+
+    /// Deserialize query-string from a `&[u8]` with this configuration.
+    pub fn deserialize_bytes<'de, T: de::Deserialize<'de>>(&self, input: &'de [u8]) -> Result<T> {
+        self.parse_bytes(input)?.deserialize()
+    }
+
+    /// Deserialize query-string from a `&str` with this configuration.
+    pub fn deserialize_str<'de, T: de::Deserialize<'de>>(&self, input: &'de str) -> Result<T> {
+        self.deserialize_bytes(input.as_bytes())
+    }
+
+    /// Deserialize the keys of query-string nested under `path` with this configuration.
+    /// See [`ParsedQuery::deserialize_at`].
+    pub fn deserialize_str_at<'de, T: de::Deserialize<'de>>(
+        &self,
+        path: &str,
+        input: &'de str,
+    ) -> Result<T> {
+        self.parse_str(input)?.deserialize_at(path)
+    }
+
+    /// Deserialize query-string from a `&str` into an existing value with this configuration.
+    /// See [`ParsedQuery::deserialize_in_place`].
+    ///
+    /// Only a type whose `deserialize_in_place` is written by hand keeps the fields the query lacks.
+    /// A `#[derive(Deserialize)]` type is replaced as a whole: its `#[serde(skip)]` fields are reset to
+    /// their default, and a query that lacks one of its required fields fails with "missing field".
+    /// To update such a value from a partial query, implement `deserialize_in_place` so that it
+    /// overwrites only the fields whose keys it visits, and `deserialize` on top of it:
+    ///
+    /// ```
+    /// use serde::de::{Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+    /// use serde_structuredqs::DeserializerConfig;
+    /// use std::fmt;
+    ///
+    /// #[derive(Debug, Default, PartialEq)]
+    /// struct Settings {
+    ///     name: String,
+    ///     limit: u32,
+    ///     // Not part of the query.
+    ///     connections: usize,
+    /// }
+    ///
+    /// impl<'de> Deserialize<'de> for Settings {
+    ///     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    ///         let mut settings = Settings::default();
+    ///         Settings::deserialize_in_place(deserializer, &mut settings)?;
+    ///         Ok(settings)
+    ///     }
+    ///
+    ///     fn deserialize_in_place<D: Deserializer<'de>>(
+    ///         deserializer: D,
+    ///         place: &mut Self,
+    ///     ) -> Result<(), D::Error> {
+    ///         struct Fields<'a>(&'a mut Settings);
+    ///
+    ///         impl<'de, 'a> Visitor<'de> for Fields<'a> {
+    ///             type Value = ();
+    ///
+    ///             fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    ///                 f.write_str("struct Settings")
+    ///             }
+    ///
+    ///             fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+    ///                 while let Some(key) = map.next_key::<String>()? {
+    ///                     match key.as_str() {
+    ///                         "name" => self.0.name = map.next_value()?,
+    ///                         "limit" => self.0.limit = map.next_value()?,
+    ///                         _ => {
+    ///                             map.next_value::<IgnoredAny>()?;
+    ///                         }
+    ///                     }
+    ///                 }
+    ///                 Ok(())
+    ///             }
+    ///         }
+    ///
+    ///         deserializer.deserialize_struct("Settings", &["name", "limit"], Fields(place))
+    ///     }
+    /// }
+    ///
+    /// let mut settings = Settings { name: String::from("default"), limit: 10, connections: 4 };
+    /// DeserializerConfig::new().deserialize_str_in_place("limit=20", &mut settings).unwrap();
+    /// assert_eq!(
+    ///     settings,
+    ///     Settings { name: String::from("default"), limit: 20, connections: 4 }
+    /// );
+    /// ```
+    ///
+    /// A nested struct is kept the same way by handing its value to its own `deserialize_in_place`,
+    /// through a [`DeserializeSeed`](de::DeserializeSeed) passed to `next_value_seed`.
+    pub fn deserialize_str_in_place<'de, T: de::Deserialize<'de>>(
+        &self,
+        input: &'de str,
+        place: &mut T,
+    ) -> Result<()> {
+        self.parse_str(input)?.deserialize_in_place(place)
+    }
+
+    /// Deserialize already decoded `(key, value)` pairs with this configuration.
+    ///
+    /// Keys are split into nested segments at `.` and sequences at the separators, as in a query-string,
+    /// but neither keys nor values are percent-decoded.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_structuredqs::DeserializerConfig;
+    /// use std::collections::HashMap;
+    ///
+    /// #[derive(Debug, Deserialize, Eq, PartialEq)]
+    /// struct Params {
+    ///     q: String,
+    ///     page: Page,
+    /// }
+    ///
+    /// #[derive(Debug, Deserialize, Eq, PartialEq)]
+    /// struct Page {
+    ///     size: u32,
+    /// }
+    ///
+    /// let pairs = HashMap::from([("q", "100%"), ("page.size", "20")]);
+    /// assert_eq!(
+    ///     DeserializerConfig::new().deserialize_pairs::<Params, _, _, _>(pairs).unwrap(),
+    ///     Params { q: String::from("100%"), page: Page { size: 20 } }
+    /// );
+    /// ```
+    pub fn deserialize_pairs<T, I, K, V>(&self, pairs: I) -> Result<T>
+    where
+        T: de::DeserializeOwned,
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let (input, spans) = parser::lay_out(pairs);
+        let pairs = Parser::new(input.as_bytes(), *self).parse_decoded(&spans)?;
+        T::deserialize(LevelDeserializer(pairs.root()))
+    }
+
+    /// Deserialize query-string read from an `io::Read` with this configuration.
+    ///
+    /// The input is read in chunks and checked against the limits as it arrives,
+    /// so reading stops at the first chunk that exceeds one of them.
+    pub fn deserialize_reader<T: de::DeserializeOwned, R: io::Read>(
+        &self,
+        mut reader: R,
+    ) -> Result<T> {
+        let mut parser = IncrementalParser::new(*self);
+        let mut chunk = [0; 8192];
+        loop {
+            match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(len) => parser.push(&chunk[..len])?,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        parser.finish()
+    }
+}
@@ -1,8 +1,9 @@
 use crate::{
     de::{
-        key::KeyDeserializer,
-        level::{Level, LevelDeserializer},
-        parser::Parser,
+        config::DeserializerConfig,
+        element::Raw,
+        key::{KeyDeserializer, KeyMatching},
+        level::{Children, Level, LevelDeserializer},
     },
     error::{Error, Result},
 };
@@ -11,40 +12,70 @@ use serde::de::{self, Error as _};
 use serde::forward_to_deserialize_any;
 
 use std::borrow::Cow;
-use std::collections::btree_map::{BTreeMap, IntoIter};
 use std::iter::Iterator;
-use std::str;
+
+/// The value of the last key returned by a [`Deserializer`].
+enum Value<'s, 'de> {
+    Level(Level<'s, 'de>),
+    /// An error message and the position in the input it refers to.
+    Invalid(String, usize),
+}
 
 /// A deserializer for the querystring format.
 ///
 /// Supported top-level outputs are structs and maps.
-pub(crate) struct Deserializer<'a> {
-    pub(crate) iter: IntoIter<Cow<'a, str>, Level<'a>>,
-    pub(crate) value: Option<Level<'a>>,
+pub(crate) struct Deserializer<'s, 'de> {
+    children: Children<'s, 'de>,
+    value: Option<Value<'s, 'de>>,
+    config: DeserializerConfig,
+    /// The fields of the struct being deserialized, if keys are not matched exactly.
+    fields: &'static [&'static str],
+    /// The number of keys matching each field and the last position they refer to.
+    matches: Vec<(usize, usize)>,
 }
 
-impl<'a> Deserializer<'a> {
-    pub(crate) fn with_map(map: BTreeMap<Cow<'a, str>, Level<'a>>) -> Self {
+impl<'s, 'de> Deserializer<'s, 'de> {
+    pub(crate) fn new(level: Level<'s, 'de>) -> Self {
         Deserializer {
-            iter: map.into_iter(),
+            children: level.children(),
             value: None,
+            config: level.config(),
+            fields: &[],
+            matches: Vec::new(),
         }
     }
 
-    /// Returns a new `Deserializer<'a>`.
-    pub(crate) fn with_bytes(input: &'a [u8]) -> Result<Self> {
-        Parser::new(input).as_deserializer()
+    /// Prepare to match keys against `fields` under the configured `KeyMatching`
+    /// and count the keys matching each field.
+    fn match_keys(&mut self, fields: &'static [&'static str]) {
+        let key_matching = self.config.key_matching;
+        if key_matching == KeyMatching::Exact {
+            return;
+        }
+
+        self.matches = vec![(0, 0); fields.len()];
+        for (key, level) in self.children {
+            if let Some(i) = fields
+                .iter()
+                .position(|field| key_matching.matches(field, key))
+            {
+                let (count, position) = &mut self.matches[i];
+                *count += 1;
+                *position = (*position).max(level.position());
+            }
+        }
+        self.fields = fields;
     }
 }
 
-impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+impl<'s, 'de> de::Deserializer<'de> for Deserializer<'s, 'de> {
     type Error = Error;
 
     fn deserialize_any<V>(mut self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        if self.iter.next().is_none() {
+        if self.children.next().is_none() {
             return visitor.visit_unit();
         }
 
@@ -59,14 +90,15 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
     }
 
     fn deserialize_struct<V>(
-        self,
+        mut self,
         _name: &'static str,
-        _fields: &'static [&'static str],
+        fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
+        self.match_keys(fields);
         self.deserialize_map(visitor)
     }
 
@@ -149,31 +181,52 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
     }
 }
 
-impl<'de> de::MapAccess<'de> for Deserializer<'de> {
+impl<'s, 'de> de::MapAccess<'de> for Deserializer<'s, 'de> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
     where
         K: de::DeserializeSeed<'de>,
     {
-        if let Some((key, value)) = self.iter.next() {
-            self.value = Some(value);
-            seed.deserialize(KeyDeserializer(key)).map(Some)
-        } else {
-            Ok(None)
-        }
+        let Some((key, level)) = self.children.next() else {
+            return Ok(None);
+        };
+        let matched = self
+            .fields
+            .iter()
+            .position(|field| self.config.key_matching.matches(field, key));
+        let key = match (matched, key) {
+            (Some(i), _) => {
+                let (count, position) = self.matches[i];
+                self.value = Some(if count > 1 {
+                    let error = format!("multiple values for one key: \"{}\"", self.fields[i]);
+                    Value::Invalid(error, position)
+                } else {
+                    Value::Level(level)
+                });
+                Raw::Input(self.fields[i])
+            }
+            (None, key) => {
+                self.value = Some(Value::Level(level));
+                match key {
+                    Cow::Borrowed(key) => Raw::Input(key),
+                    Cow::Owned(key) => Raw::Decoded(key),
+                }
+            }
+        };
+        seed.deserialize(KeyDeserializer(key)).map(Some)
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
     where
         V: de::DeserializeSeed<'de>,
     {
-        if let Some(v) = self.value.take() {
-            seed.deserialize(LevelDeserializer(v))
-        } else {
-            Err(Error::custom(
+        match self.value.take() {
+            Some(Value::Level(level)) => seed.deserialize(LevelDeserializer(level)),
+            Some(Value::Invalid(e, position)) => Err(Error::parse_error(e, position)),
+            None => Err(Error::custom(
                 "Somehow the map was empty after a non-empty key was returned",
-            ))
+            )),
         }
     }
 }
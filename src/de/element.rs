@@ -0,0 +1,162 @@
+use crate::{
+    de::{
+        config::DeserializerConfig,
+        level::{Separated, Source},
+    },
+    error::{Error, Result},
+    sequence,
+};
+
+use serde::de::{self, Error as _, IntoDeserializer};
+use serde::forward_to_deserialize_any;
+
+use std::borrow::Cow;
+
+macro_rules! forward_parsable_to_deserialize_any {
+    ($($ty:ident => $meth:ident,)*) => {
+        $(
+            fn $meth<V>(self, visitor: V) -> Result<V::Value> where V: de::Visitor<'de> {
+                match self.value().parse::<$ty>() {
+                    Ok(val) => val.into_deserializer().$meth(visitor),
+                    Err(e) => Err(Error::custom(e))
+                }
+            }
+        )*
+    }
+}
+
+/// The still escaped text of an element of a sequence.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Raw<'de, 's> {
+    /// Borrowed from the input, so that it can be deserialized into `&'de str`.
+    Input(&'de str),
+    /// Borrowed from a value that had to be decoded into an owned string.
+    Decoded(&'s str),
+}
+
+impl<'de, 's> Raw<'de, 's> {
+    pub fn as_str(&self) -> &str {
+        match *self {
+            Raw::Input(s) => s,
+            Raw::Decoded(s) => s,
+        }
+    }
+}
+
+/// Deserializer for a single, still escaped element of a sequence.
+pub(crate) struct ElementDeserializer<'de, 's>(pub Raw<'de, 's>, pub DeserializerConfig);
+
+impl<'de, 's> ElementDeserializer<'de, 's> {
+    fn is_none(&self) -> bool {
+        self.0.as_str() == sequence::NONE
+    }
+
+    /// Resolve markers and escape sequences into the value of the element.
+    fn value(&self) -> Cow<'_, str> {
+        match self.0.as_str() {
+            sequence::EMPTY => Cow::Borrowed(""),
+            s => sequence::unescape(s),
+        }
+    }
+}
+
+impl<'de, 's> de::Deserializer<'de> for ElementDeserializer<'de, 's> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if self.is_none() {
+            return visitor.visit_unit();
+        }
+        match self.0 {
+            Raw::Input(sequence::EMPTY) => visitor.visit_borrowed_str(""),
+            Raw::Input(s) => match sequence::unescape(s) {
+                Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+                Cow::Owned(s) => visitor.visit_string(s),
+            },
+            Raw::Decoded(_) => match self.value() {
+                Cow::Borrowed(s) => visitor.visit_str(s),
+                Cow::Owned(s) => visitor.visit_string(s),
+            },
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if self.is_none() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if self.is_none() {
+            visitor.visit_unit()
+        } else {
+            Err(Error::custom("expected unit"))
+        }
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_seq(Separated::new(Source::Raw(self.0), self.1)?)
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        map
+        struct
+        char
+        str
+        string
+        bytes
+        enum
+        byte_buf
+        unit_struct
+        newtype_struct
+        identifier
+        ignored_any
+    }
+
+    forward_parsable_to_deserialize_any! {
+        bool => deserialize_bool,
+        u8 => deserialize_u8,
+        u16 => deserialize_u16,
+        u32 => deserialize_u32,
+        u64 => deserialize_u64,
+        i8 => deserialize_i8,
+        i16 => deserialize_i16,
+        i32 => deserialize_i32,
+        i64 => deserialize_i64,
+        f32 => deserialize_f32,
+        f64 => deserialize_f64,
+    }
+}
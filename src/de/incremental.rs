@@ -0,0 +1,150 @@
+use crate::{
+    de::config::DeserializerConfig,
+    error::{Error, Result},
+};
+
+use serde::de;
+
+/// A push-style parser of query-strings that arrive in chunks, such as a request body read from a socket.
+///
+/// Chunks may split keys, values and even percent-encoded bytes anywhere. The configured limits are checked
+/// as each chunk arrives, so an oversized input fails without being buffered whole, and
+/// [`finish`](IncrementalParser::finish) deserializes the buffered input exactly as
+/// [`DeserializerConfig::deserialize_bytes`] would.
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde_structuredqs::{DeserializerConfig, IncrementalParser};
+///
+/// #[derive(Debug, Deserialize, Eq, PartialEq)]
+/// struct Params {
+///     name: String,
+///     ids: Vec<u32>,
+/// }
+///
+/// let mut parser = IncrementalParser::new(DeserializerConfig::new());
+/// for chunk in ["na", "me=%E3%", "81%BB&ids=1", "%2C2"] {
+///     parser.push(chunk.as_bytes()).unwrap();
+/// }
+/// assert_eq!(
+///     parser.finish::<Params>().unwrap(),
+///     Params { name: String::from("ほ"), ids: vec![1, 2] }
+/// );
+/// ```
+#[derive(Debug)]
+pub struct IncrementalParser {
+    buffer: Vec<u8>,
+    config: DeserializerConfig,
+    /// The number of pairs started so far, empty pairs excluded.
+    pairs: usize,
+    /// Whether the last pushed byte belongs to a pair that has not been ended by `&` yet.
+    in_pair: bool,
+    /// The length of the key of the current pair, or of what has arrived of it.
+    key_len: usize,
+    /// The length of the value of the current pair, or `None` if its `=` has not arrived.
+    value_len: Option<usize>,
+    /// The number of `.` in the key of the current pair.
+    dots: usize,
+}
+
+impl IncrementalParser {
+    pub fn new(config: DeserializerConfig) -> Self {
+        Self {
+            buffer: Vec::new(),
+            config,
+            pairs: 0,
+            in_pair: false,
+            key_len: 0,
+            value_len: None,
+            dots: 0,
+        }
+    }
+
+    /// Append the next chunk of the input, failing as soon as it exceeds a limit.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<()> {
+        if chunk.len() > self.config.max_input_len - self.buffer.len() {
+            return Err(Error::limit_exceeded(
+                "input length",
+                self.config.max_input_len,
+            ));
+        }
+        self.scan(chunk)?;
+        self.buffer.extend_from_slice(chunk);
+        Ok(())
+    }
+
+    /// Deserialize the input pushed so far.
+    pub fn finish<T: de::DeserializeOwned>(self) -> Result<T> {
+        self.config.deserialize_bytes(&self.buffer)
+    }
+
+    /// Track the pairs of the chunk, checking the same limits as the parser does for a whole input.
+    fn scan(&mut self, chunk: &[u8]) -> Result<()> {
+        let mut rest = chunk;
+        while !rest.is_empty() {
+            let found = match self.value_len {
+                None => memchr::memchr3(b'&', b'=', b'.', rest),
+                Some(_) => memchr::memchr(b'&', rest),
+            };
+            let run = found.unwrap_or(rest.len());
+            if run > 0 {
+                self.start_pair()?;
+                match &mut self.value_len {
+                    None => self.key_len += run,
+                    Some(value_len) => *value_len += run,
+                }
+            }
+            match found.map(|i| rest[i]) {
+                Some(b'&') => {
+                    self.in_pair = false;
+                    self.key_len = 0;
+                    self.value_len = None;
+                    self.dots = 0;
+                }
+                Some(b'=') => {
+                    self.start_pair()?;
+                    self.value_len = Some(0);
+                }
+                Some(_) => {
+                    self.start_pair()?;
+                    self.key_len += 1;
+                    self.dots += 1;
+                }
+                None => {}
+            }
+            self.check_pair()?;
+            rest = &rest[(run + 1).min(rest.len())..];
+        }
+        Ok(())
+    }
+
+    fn start_pair(&mut self) -> Result<()> {
+        if !self.in_pair {
+            self.in_pair = true;
+            self.pairs += 1;
+            if self.pairs > self.config.max_pairs {
+                return Err(Error::limit_exceeded(
+                    "number of pairs",
+                    self.config.max_pairs,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_pair(&self) -> Result<()> {
+        if self.key_len > self.config.max_key_len {
+            return Err(Error::limit_exceeded("key length", self.config.max_key_len));
+        }
+        if self.value_len.unwrap_or(0) > self.config.max_value_len {
+            return Err(Error::limit_exceeded(
+                "value length",
+                self.config.max_value_len,
+            ));
+        }
+        if self.dots >= self.config.max_depth {
+            return Err(Error::limit_exceeded("depth", self.config.max_depth));
+        }
+        Ok(())
+    }
+}
@@ -1,16 +1,18 @@
-use crate::error::{Error, Result};
+use crate::{
+    de::element::Raw,
+    error::{Error, Result},
+};
 
 use serde::de::{self, Error as _, IntoDeserializer};
 use serde::forward_to_deserialize_any;
 
-use std::borrow::Cow;
 use std::str;
 
 macro_rules! forward_parsable_to_deserialize_any {
     ($($ty:ident => $meth:ident,)*) => {
         $(
             fn $meth<V>(self, visitor: V) -> Result<V::Value> where V: de::Visitor<'de> {
-                match self.0.parse::<$ty>() {
+                match self.0.as_str().parse::<$ty>() {
                     Ok(val) => val.into_deserializer().$meth(visitor),
                     Err(e) => Err(Error::custom(e))
                 }
@@ -19,16 +21,51 @@ macro_rules! forward_parsable_to_deserialize_any {
     }
 }
 
-pub(crate) struct KeyDeserializer<'a>(pub Cow<'a, str>);
+/// How the keys of a query-string are matched against the fields of a struct.
+///
+/// Keys are matched at every nesting level. When several keys match the same field,
+/// the field is reported as having multiple values, just as for a repeated key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyMatching {
+    /// Keys must be equal to the field names.
+    #[default]
+    Exact,
+    /// Keys match field names regardless of letter case, so `Keyword` matches `keyword`.
+    CaseInsensitive,
+    /// Keys match field names regardless of letter case and of the `_` and `-` between words,
+    /// so `pageSize`, `page_size` and `page-size` all match `page_size`.
+    StyleInsensitive,
+}
+
+impl KeyMatching {
+    fn normalize(self, key: &str) -> impl Iterator<Item = char> + '_ {
+        key.chars()
+            .filter(move |&c| !(self == KeyMatching::StyleInsensitive && (c == '_' || c == '-')))
+            .flat_map(char::to_lowercase)
+    }
+
+    /// Returns whether `key` matches the field `field`.
+    pub(crate) fn matches(self, field: &str, key: &str) -> bool {
+        match self {
+            KeyMatching::Exact => field == key,
+            _ => self.normalize(field).eq(self.normalize(key)),
+        }
+    }
+}
+
+pub(crate) struct KeyDeserializer<'de, 's>(pub Raw<'de, 's>);
 
-impl<'de> de::Deserializer<'de> for KeyDeserializer<'de> {
+impl<'de, 's> de::Deserializer<'de> for KeyDeserializer<'de, 's> {
     type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        self.0.into_deserializer().deserialize_any(visitor)
+        match self.0 {
+            Raw::Input(s) => visitor.visit_borrowed_str(s),
+            Raw::Decoded(s) => visitor.visit_str(s),
+        }
     }
 
     forward_to_deserialize_any! {
@@ -1,5 +1,11 @@
 use crate::{
-    de::{deserializer::Deserializer, key::KeyDeserializer},
+    de::{
+        charset::Charset,
+        config::DeserializerConfig,
+        deserializer::Deserializer,
+        element::{ElementDeserializer, Raw},
+        parser::{self, Span},
+    },
     error::{Error, Result},
 };
 
@@ -8,12 +14,7 @@ use serde::{
     forward_to_deserialize_any,
 };
 
-use std::{
-    borrow::Cow,
-    collections::btree_map::{BTreeMap, Entry},
-    str,
-    vec::IntoIter,
-};
+use std::{borrow::Cow, ops::Range, str, vec::IntoIter};
 
 macro_rules! deserialize_primitive {
     ($ty:ident, $method:ident, $visit_method:ident) => {
@@ -21,24 +22,21 @@ macro_rules! deserialize_primitive {
         where
             V: de::Visitor<'de>,
         {
-            match self.0 {
-                Level::Nested(_) => Err(de::Error::custom(format!(
+            match self.0.kind() {
+                Kind::Nested => Err(de::Error::custom(format!(
                     "Expected: {:?}, got a Map",
                     stringify!($ty)
                 ))),
-                Level::Invalid(e) => Err(de::Error::custom(e)),
-                Level::UnInitialized => Err(de::Error::custom(
-                    "attempted to deserialize uninitialized value",
-                )),
-                Level::Flat(x) => {
+                Kind::Invalid(e, position) => Err(Error::parse_error(e, position)),
+                Kind::Flat(span) => {
+                    let x = self.0.pairs.decode(span)?;
                     if let Ok(x) = x.parse::<$ty>() {
                         visitor.$visit_method(x)
                     } else {
-                        Err(de::Error::custom(format!(
-                            "Expected {:?}, but got {}",
-                            stringify!($ty),
-                            x
-                        )))
+                        Err(Error::parse_error(
+                            format!("Expected {:?}, but got {}", stringify!($ty), x),
+                            span.start,
+                        ))
                     }
                 }
             }
@@ -46,81 +44,306 @@ macro_rules! deserialize_primitive {
     };
 }
 
+/// A pair whose key has been split into decoded segments, stored as a range of [`Pairs::segments`].
+#[derive(Clone, Debug)]
+pub(crate) struct Entry {
+    /// The first bytes of the first segment, which order most entries without comparing strings.
+    prefix: u64,
+    segments: Range<usize>,
+    key: Span,
+    value: Option<Span>,
+}
+
+impl Entry {
+    /// The span of the raw value, which is empty at the end of the key if the pair has no value.
+    fn value_span(&self) -> Span {
+        self.value.unwrap_or(Span::new(self.key.end, self.key.end))
+    }
+
+    fn depth(&self) -> usize {
+        self.segments.len()
+    }
+}
+
+/// All pairs of a query-string, sorted by their decoded keys.
+///
+/// Rather than a map per nesting level, a level is the range of entries that share their leading key segments,
+/// so parsing fills the same two vectors however deeply the keys are nested.
+/// Values are kept as spans of the input and only percent-decoded when they are deserialized.
 #[derive(Debug)]
-pub(crate) enum Level<'a> {
-    Nested(BTreeMap<Cow<'a, str>, Level<'a>>),
-    Flat(Cow<'a, str>),
-    Invalid(String),
-    UnInitialized,
+pub(crate) struct Pairs<'de> {
+    input: &'de [u8],
+    segments: Vec<Cow<'de, str>>,
+    entries: Vec<Entry>,
+    config: DeserializerConfig,
+    charset: Charset,
+    /// Whether the values are percent-encoded, rather than already decoded pairs laid out in the input.
+    encoded: bool,
 }
 
-impl<'a> Level<'a> {
-    /// If this `Level` value is indeed a map, then attempt to insert
-    /// `value` for key `key`.
-    /// Returns error if `self` is not a map, or already has an entry for that
-    /// key.
-    pub fn insert_map_value(&mut self, key: Cow<'a, str>, value: Cow<'a, str>) {
-        match *self {
-            Level::Nested(ref mut map) => {
-                match map.entry(key) {
-                    Entry::Occupied(mut o) => {
-                        let key = o.key();
-                        let error = format!("multiple values for one key: \"{}\"", key);
-                        // Throw away old result; map is now invalid anyway.
-                        o.insert(Level::Invalid(error));
-                    }
-                    Entry::Vacant(vm) => {
-                        // Map is empty, result is None
-                        vm.insert(Level::Flat(value));
-                    }
-                }
-            }
-            Level::UnInitialized => {
-                let mut map = BTreeMap::default();
-                map.insert(key, Level::Flat(value));
-                *self = Level::Nested(map);
-            }
-            _ => {
-                *self = Level::Invalid(
-                    "attempted to insert map value into non-map structure".to_string(),
-                );
-            }
+impl<'de> Pairs<'de> {
+    pub fn new(input: &'de [u8], config: DeserializerConfig, charset: Charset) -> Self {
+        // Every pair and key segment needs a separator, so counting them bounds both vectors.
+        let pairs = (memchr::memchr_iter(b'&', input).count() + 1).min(config.max_pairs);
+        let segments = (pairs + memchr::memchr_iter(b'.', input).count())
+            .min(pairs.saturating_mul(config.max_depth));
+        Self {
+            input,
+            segments: Vec::with_capacity(segments),
+            entries: Vec::with_capacity(pairs),
+            config,
+            charset,
+            encoded: true,
+        }
+    }
+
+    /// Mark the values of the input as already decoded.
+    pub fn decoded(mut self) -> Self {
+        self.encoded = false;
+        self
+    }
+
+    /// Add a decoded key segment of the next entry.
+    pub fn push_segment(&mut self, segment: Cow<'de, str>) {
+        self.segments.push(segment);
+    }
+
+    /// Add an entry with the segments pushed since the previous one. A key without any segment carries nothing.
+    pub fn push_entry(&mut self, key: Span, value: Option<Span>) {
+        let start = self.entries.last().map_or(0, |entry| entry.segments.end);
+        if let Some(first) = self.segments.get(start) {
+            let mut prefix = [0; 8];
+            let len = first.len().min(8);
+            prefix[..len].copy_from_slice(&first.as_bytes()[..len]);
+            self.entries.push(Entry {
+                prefix: u64::from_be_bytes(prefix),
+                segments: start..self.segments.len(),
+                key,
+                value,
+            });
+        }
+    }
+
+    /// Sort the entries by key, keeping the pairs with equal keys in input order.
+    pub fn sort(&mut self) {
+        let segments = &self.segments;
+        // Entries are pushed in input order, so the start of their segments breaks ties.
+        let compare = |a: &Entry, b: &Entry| {
+            a.prefix.cmp(&b.prefix).then_with(|| {
+                (&segments[a.segments.clone()], a.segments.start)
+                    .cmp(&(&segments[b.segments.clone()], b.segments.start))
+            })
         };
+        if !self.entries.is_sorted_by(|a, b| compare(a, b).is_le()) {
+            self.entries.sort_unstable_by(compare);
+        }
+    }
+
+    pub fn root(&self) -> Level<'_, 'de> {
+        Level {
+            pairs: self,
+            entries: &self.entries,
+            depth: 0,
+        }
+    }
+
+    /// The decoded key segment of the entry at `depth`.
+    fn segment(&self, entry: &Entry, depth: usize) -> &Cow<'de, str> {
+        &self.segments[entry.segments.start + depth]
+    }
+
+    /// Decode every pair in sorted order, with the segments of its key.
+    /// A key without value is paired with an empty value.
+    pub fn to_decoded_pairs(&self) -> Result<Vec<(Vec<String>, String)>> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let key = self.segments[entry.segments.clone()]
+                    .iter()
+                    .map(|segment| segment.to_string())
+                    .collect();
+                let value = self.decode(entry.value_span())?.into_owned();
+                Ok((key, value))
+            })
+            .collect()
+    }
+
+    /// Percent-decode a value of the input, or borrow it if it is already decoded.
+    fn decode(&self, span: Span) -> Result<Cow<'de, str>> {
+        if !self.encoded {
+            return str::from_utf8(&self.input[span.start..span.end])
+                .map(Cow::Borrowed)
+                .map_err(|e| Error::parse_error(e, span.start));
+        }
+        parser::decode(
+            &self.input[span.start..span.end],
+            self.config.plus_as_space,
+            self.charset,
+        )
+        .map_err(|e| Error::parse_error(e, span.start))
+    }
+}
+
+/// What a [`Level`] holds.
+pub(crate) enum Kind {
+    /// Keys nested one segment deeper.
+    Nested,
+    /// The raw value of a single pair.
+    Flat(Span),
+    /// An error message and the position in the input it refers to.
+    Invalid(String, usize),
+}
+
+/// The entries whose keys share the same first `depth` segments.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Level<'s, 'de> {
+    pairs: &'s Pairs<'de>,
+    entries: &'s [Entry],
+    depth: usize,
+}
+
+impl<'s, 'de> Level<'s, 'de> {
+    pub fn kind(&self) -> Kind {
+        // Entries ending at this depth sort before the ones with nested keys.
+        let flat = self
+            .entries
+            .iter()
+            .take_while(|entry| entry.depth() == self.depth)
+            .count();
+        if flat == 0 {
+            return Kind::Nested;
+        }
+
+        let key = self.pairs.segment(&self.entries[0], self.depth - 1);
+        if flat > 1 {
+            let error = format!("multiple values for one key: \"{}\"", key);
+            Kind::Invalid(error, self.entries[1].value_span().start)
+        } else if self.entries.len() > 1 {
+            let error = format!(
+                "a key cannot have both a value and nested keys: \"{}\"",
+                key
+            );
+            let nested = Level {
+                entries: &self.entries[1..],
+                ..*self
+            };
+            let position = self.entries[0].value_span().start.max(nested.position());
+            Kind::Invalid(error, position)
+        } else {
+            Kind::Flat(self.entries[0].value_span())
+        }
+    }
+
+    /// Percent-decode a value of this level.
+    pub fn decode(&self, span: Span) -> Result<Cow<'de, str>> {
+        self.pairs.decode(span)
+    }
+
+    /// Returns whether no key starts with the segments of this level.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the first position in the input this level refers to.
+    pub fn position(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|entry| entry.value_span().start)
+            .min()
+            .unwrap_or(0)
+    }
+
+    pub fn config(&self) -> DeserializerConfig {
+        self.pairs.config
+    }
+
+    /// The level of the keys one segment deeper that start with `key`, which has no entries if there are none.
+    pub fn child(&self, key: &str) -> Level<'s, 'de> {
+        let Level {
+            pairs,
+            entries,
+            depth,
+        } = *self;
+        // Entries are sorted by their segments, so the ones ending at this depth come first
+        // and the others are sorted by their segment at this depth.
+        let flat = entries.partition_point(|entry| entry.depth() == depth);
+        let nested = &entries[flat..];
+        let start = nested.partition_point(|entry| pairs.segment(entry, depth).as_ref() < key);
+        let len = nested[start..].partition_point(|entry| pairs.segment(entry, depth) == key);
+        Level {
+            pairs,
+            entries: &nested[start..start + len],
+            depth: depth + 1,
+        }
+    }
+
+    /// Iterate over the keys one segment deeper and their levels.
+    ///
+    /// Only meaningful for a level of [`Kind::Nested`].
+    pub fn children(&self) -> Children<'s, 'de> {
+        Children { level: *self }
+    }
+}
+
+/// Iterator over the children of a [`Level`], in key order.
+#[derive(Clone, Copy)]
+pub(crate) struct Children<'s, 'de> {
+    level: Level<'s, 'de>,
+}
+
+impl<'s, 'de> Iterator for Children<'s, 'de> {
+    type Item = (&'s Cow<'de, str>, Level<'s, 'de>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Level {
+            pairs,
+            entries,
+            depth,
+        } = self.level;
+        let key = pairs.segment(entries.first()?, depth);
+        let len = entries.partition_point(|entry| pairs.segment(entry, depth) == key);
+        let (child, rest) = entries.split_at(len);
+        self.level.entries = rest;
+        Some((
+            key,
+            Level {
+                pairs,
+                entries: child,
+                depth: depth + 1,
+            },
+        ))
     }
 }
 
-pub(crate) struct LevelDeserializer<'a>(pub Level<'a>);
+pub(crate) struct LevelDeserializer<'s, 'de>(pub Level<'s, 'de>);
 
-impl<'a> LevelDeserializer<'a> {
-    fn into_deserializer(self) -> Result<Deserializer<'a>> {
-        match self.0 {
-            Level::Nested(map) => Ok(Deserializer::with_map(map)),
-            Level::Invalid(e) => Err(de::Error::custom(e)),
-            l => Err(de::Error::custom(format!(
-                "could not convert {:?} to Deserializer<'a>",
-                l
+impl<'s, 'de> LevelDeserializer<'s, 'de> {
+    pub fn into_deserializer(self) -> Result<Deserializer<'s, 'de>> {
+        match self.0.kind() {
+            Kind::Nested => Ok(Deserializer::new(self.0)),
+            Kind::Invalid(e, position) => Err(Error::parse_error(e, position)),
+            Kind::Flat(span) => Err(de::Error::custom(format!(
+                "could not convert the value at {} to a map",
+                span.start
             ))),
         }
     }
 }
 
-impl<'de> de::Deserializer<'de> for LevelDeserializer<'de> {
+impl<'s, 'de> de::Deserializer<'de> for LevelDeserializer<'s, 'de> {
     type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        match self.0 {
-            Level::Nested(_) => self.into_deserializer()?.deserialize_map(visitor),
-            Level::Flat(x) => match x {
+        match self.0.kind() {
+            Kind::Nested => self.into_deserializer()?.deserialize_map(visitor),
+            Kind::Flat(span) => match self.0.pairs.decode(span)? {
                 Cow::Owned(s) => visitor.visit_string(s),
                 Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
             },
-            Level::Invalid(e) => Err(de::Error::custom(e)),
-            Level::UnInitialized => Err(de::Error::custom(
-                "attempted to deserialize uninitialized value",
-            )),
+            Kind::Invalid(e, position) => Err(Error::parse_error(e, position)),
         }
     }
 
@@ -128,8 +351,8 @@ impl<'de> de::Deserializer<'de> for LevelDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        match self.0 {
-            Level::Flat(ref x) if x == "" => visitor.visit_none(),
+        match self.0.kind() {
+            Kind::Flat(span) if span.len() == 0 => visitor.visit_none(),
             _ => visitor.visit_some(self),
         }
     }
@@ -138,8 +361,8 @@ impl<'de> de::Deserializer<'de> for LevelDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        match self.0 {
-            Level::Flat(ref x) if x == "" => visitor.visit_unit(),
+        match self.0.kind() {
+            Kind::Flat(span) if span.len() == 0 => visitor.visit_unit(),
             _ => Err(de::Error::custom("expected unit".to_owned())),
         }
     }
@@ -160,22 +383,55 @@ impl<'de> de::Deserializer<'de> for LevelDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        match self.0 {
-            Level::Nested(_) => Err(de::Error::custom(format!(
-                "Expected: {:?}, got a Map",
-                stringify!($ty)
-            ))),
-            Level::Invalid(e) => Err(de::Error::custom(e)),
-            Level::UnInitialized => Err(de::Error::custom(
-                "attempted to deserialize uninitialized value",
-            )),
-            Level::Flat(x) => {
-                let seq = CommaSeparated::new(x);
-                visitor.visit_seq(seq)
+        match self.0.kind() {
+            Kind::Nested => Err(de::Error::custom("Expected: a sequence, got a Map")),
+            Kind::Invalid(e, position) => Err(Error::parse_error(e, position)),
+            Kind::Flat(span) => {
+                let source = match self.0.pairs.decode(span)? {
+                    Cow::Borrowed(x) => Source::Raw(Raw::Input(x)),
+                    Cow::Owned(x) => Source::Owned(x),
+                };
+                visitor.visit_seq(Separated::new(source, self.0.config())?)
             }
         }
     }
 
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.0.kind() {
+            Kind::Nested => self
+                .into_deserializer()?
+                .deserialize_struct(name, fields, visitor),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
     /// given the hint that this is a map, will first
     /// attempt to deserialize ordered sequences into a map
     /// otherwise, follows the any code path
@@ -206,42 +462,76 @@ impl<'de> de::Deserializer<'de> for LevelDeserializer<'de> {
         byte_buf
         unit_struct
         newtype_struct
-        tuple_struct
-        struct
         identifier
-        tuple
-        ignored_any
         // seq
         // map
     }
+
+    /// Skip the value without decoding it.
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
 }
 
-struct CommaSeparated<'a> {
-    iter: IntoIter<Cow<'a, str>>,
+/// The text of a sequence value.
+pub(crate) enum Source<'de, 's> {
+    Raw(Raw<'de, 's>),
+    /// A value that had to be decoded into an owned string.
+    Owned(String),
 }
 
-impl<'a> CommaSeparated<'a> {
-    pub fn new(raw: Cow<'a, str>) -> Self {
-        let vec: Vec<Cow<'a, str>> = match raw {
-            Cow::Borrowed(s) => s
-                .split(',')
-                .filter(|s| !s.is_empty())
-                .map(Cow::Borrowed)
-                .collect(),
-            Cow::Owned(s) => s
-                .split(',')
-                .filter(|s| !s.is_empty())
-                .map(|s| Cow::Owned(s.to_owned()))
-                .collect(),
-        };
+impl<'de, 's> Source<'de, 's> {
+    fn as_str(&self) -> &str {
+        match self {
+            Source::Raw(raw) => raw.as_str(),
+            Source::Owned(s) => s,
+        }
+    }
+}
 
-        Self {
-            iter: vec.into_iter(),
+/// Access to the elements of a sequence value split by the configured separators.
+///
+/// The elements are kept as ranges of the source, so that they are borrowed from it
+/// and only allocated when the visitor asks for an owned string.
+pub(crate) struct Separated<'de, 's> {
+    source: Source<'de, 's>,
+    ranges: IntoIter<Range<usize>>,
+    config: DeserializerConfig,
+}
+
+impl<'de, 's> Separated<'de, 's> {
+    pub fn new(source: Source<'de, 's>, config: DeserializerConfig) -> Result<Self> {
+        let raw = source.as_str();
+        // Take one more element than allowed to detect the excess without splitting the rest.
+        let max = config.max_sequence_len.saturating_add(1);
+        let ranges: Vec<Range<usize>> = config
+            .separators
+            .split(raw)
+            .take(max)
+            .map(|piece| {
+                let start = piece.as_ptr() as usize - raw.as_ptr() as usize;
+                start..start + piece.len()
+            })
+            .collect();
+        if ranges.len() > config.max_sequence_len {
+            return Err(Error::limit_exceeded(
+                "sequence length",
+                config.max_sequence_len,
+            ));
         }
+
+        Ok(Self {
+            source,
+            ranges: ranges.into_iter(),
+            config,
+        })
     }
 }
 
-impl<'de, 'a: 'de> de::SeqAccess<'de> for CommaSeparated<'a> {
+impl<'de, 's> de::SeqAccess<'de> for Separated<'de, 's> {
     type Error = Error;
 
     fn next_element_seed<T>(
@@ -251,10 +541,19 @@ impl<'de, 'a: 'de> de::SeqAccess<'de> for CommaSeparated<'a> {
     where
         T: de::DeserializeSeed<'de>,
     {
-        if let Some(element) = self.iter.next() {
-            seed.deserialize(KeyDeserializer(element)).map(Some)
-        } else {
-            Ok(None)
-        }
+        let Some(range) = self.ranges.next() else {
+            return Ok(None);
+        };
+        let element = match &self.source {
+            Source::Raw(Raw::Input(s)) => Raw::Input(&s[range]),
+            Source::Raw(Raw::Decoded(s)) => Raw::Decoded(&s[range]),
+            Source::Owned(s) => Raw::Decoded(&s[range]),
+        };
+        seed.deserialize(ElementDeserializer(element, self.config))
+            .map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.ranges.len())
     }
 }
@@ -1,17 +1,84 @@
+pub mod charset;
+pub mod config;
 pub mod deserializer;
+pub mod element;
+pub mod incremental;
 pub mod key;
 pub mod level;
+pub mod parsed;
 pub mod parser;
 
-use crate::{de::deserializer::Deserializer, error::Result};
+use crate::{de::config::DeserializerConfig, error::Result};
 use serde::de;
+use std::io;
 
 /// Deserialize query-string from a `&[u8]`.
 pub fn from_bytes<'de, T: de::Deserialize<'de>>(input: &'de [u8]) -> Result<T> {
-    T::deserialize(Deserializer::with_bytes(input)?)
+    DeserializerConfig::default().deserialize_bytes(input)
 }
 
 /// Deserialize query-string from a `&str`.
 pub fn from_str<'de, T: de::Deserialize<'de>>(input: &'de str) -> Result<T> {
     from_bytes(input.as_bytes())
 }
+
+/// Deserialize the keys of query-string nested under `path`, a `.`-separated sequence of key segments,
+/// as if they were at the top level.
+///
+/// If no key starts with `path`, an `Option` is deserialized as `None`
+/// and anything else fails with [`Error::Missing`](crate::Error::Missing).
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, Deserialize, Eq, PartialEq)]
+/// struct WidgetParams {
+///     theme: String,
+///     size: u32,
+/// }
+///
+/// let query = "w.theme=dark&w.size=3&page=2";
+/// assert_eq!(
+///     serde_structuredqs::from_str_at::<WidgetParams>("w", query).unwrap(),
+///     WidgetParams { theme: String::from("dark"), size: 3 }
+/// );
+/// assert_eq!(
+///     serde_structuredqs::from_str_at::<Option<WidgetParams>>("v", query).unwrap(),
+///     None
+/// );
+/// ```
+pub fn from_str_at<'de, T: de::Deserialize<'de>>(path: &str, input: &'de str) -> Result<T> {
+    DeserializerConfig::default().deserialize_str_at(path, input)
+}
+
+/// Deserialize query-string from a `&str` into an existing value. See [`ParsedQuery::deserialize_in_place`](crate::ParsedQuery::deserialize_in_place).
+///
+/// Only a type with a hand-written `deserialize_in_place` keeps the fields the query lacks.
+/// A `#[derive(Deserialize)]` type is replaced as a whole, resetting its `#[serde(skip)]` fields,
+/// and fails with "missing field" on a query that lacks a required field.
+/// See [`DeserializerConfig::deserialize_str_in_place`](crate::DeserializerConfig::deserialize_str_in_place)
+/// for how to write such an implementation.
+pub fn from_str_in_place<'de, T: de::Deserialize<'de>>(
+    input: &'de str,
+    place: &mut T,
+) -> Result<()> {
+    DeserializerConfig::default().deserialize_str_in_place(input, place)
+}
+
+/// Deserialize query-string read from an `io::Read`, such as a request body.
+///
+/// The input is read in chunks and checked against the limits as it arrives, see [`IncrementalParser`](crate::IncrementalParser).
+pub fn from_reader<T: de::DeserializeOwned, R: io::Read>(reader: R) -> Result<T> {
+    DeserializerConfig::default().deserialize_reader(reader)
+}
+
+/// Deserialize already decoded `(key, value)` pairs, such as a `HashMap<String, String>` given by a framework.
+/// See [`DeserializerConfig::deserialize_pairs`].
+pub fn from_pairs<T, I, K, V>(pairs: I) -> Result<T>
+where
+    T: de::DeserializeOwned,
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    DeserializerConfig::default().deserialize_pairs(pairs)
+}
@@ -0,0 +1,116 @@
+use crate::{
+    de::level::{LevelDeserializer, Pairs},
+    error::{Error, Result},
+};
+
+use serde::{de, forward_to_deserialize_any};
+
+/// A query-string parsed once, to be deserialized into any number of types.
+///
+/// Deserializing borrows the parsed query, so the values may borrow from the input as with
+/// [`from_str`](crate::from_str), and [`deserialize_at`](ParsedQuery::deserialize_at) deserializes
+/// the keys nested under a path as if they were at the top level.
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde_structuredqs::ParsedQuery;
+///
+/// #[derive(Debug, Deserialize, Eq, PartialEq)]
+/// struct Pagination {
+///     page: u32,
+/// }
+///
+/// #[derive(Debug, Deserialize, Eq, PartialEq)]
+/// struct Filters<'a> {
+///     category: &'a str,
+/// }
+///
+/// let query = ParsedQuery::parse("page=2&filter.category=books&utm.source=mail").unwrap();
+/// assert_eq!(query.deserialize::<Pagination>().unwrap(), Pagination { page: 2 });
+/// assert_eq!(
+///     query.deserialize_at::<Filters>("filter").unwrap(),
+///     Filters { category: "books" }
+/// );
+/// ```
+#[derive(Debug)]
+pub struct ParsedQuery<'de> {
+    pairs: Pairs<'de>,
+}
+
+impl<'de> ParsedQuery<'de> {
+    pub(crate) fn new(pairs: Pairs<'de>) -> Self {
+        Self { pairs }
+    }
+
+    /// Parse query-string with the default configuration.
+    pub fn parse(input: &'de str) -> Result<Self> {
+        crate::DeserializerConfig::default().parse_str(input)
+    }
+
+    /// Deserialize the whole query.
+    pub fn deserialize<T: de::Deserialize<'de>>(&self) -> Result<T> {
+        T::deserialize(LevelDeserializer(self.pairs.root()))
+    }
+
+    /// Deserialize the whole query into an existing value with [`Deserialize::deserialize_in_place`](de::Deserialize::deserialize_in_place).
+    ///
+    /// Each struct and map of the query is visited as a map of only the keys it has,
+    /// and each value is handed to the `deserialize_in_place` of its type, at any depth.
+    /// So a type that implements `deserialize_in_place` by overwriting the fields whose keys it visits
+    /// keeps the other fields, including the fields skipped by serde.
+    ///
+    /// The `deserialize_in_place` of most types, including those of `#[derive(Deserialize)]`,
+    /// replaces the value as a whole, as [`deserialize`](ParsedQuery::deserialize) would.
+    /// If deserialization fails, `place` may be left partially overwritten.
+    pub fn deserialize_in_place<T: de::Deserialize<'de>>(&self, place: &mut T) -> Result<()> {
+        T::deserialize_in_place(LevelDeserializer(self.pairs.root()), place)
+    }
+
+    /// Deserialize the keys nested under `path`, a `.`-separated sequence of decoded key segments.
+    ///
+    /// Segments are matched exactly, whatever the [`KeyMatching`](crate::KeyMatching),
+    /// and a path that is a whole key is deserialized as its value.
+    /// If no key starts with `path`, an `Option` is deserialized as `None`
+    /// and anything else fails with [`Error::Missing`](crate::Error::Missing).
+    pub fn deserialize_at<T: de::Deserialize<'de>>(&self, path: &str) -> Result<T> {
+        let mut segments = path.split('.').filter(|segment| !segment.is_empty());
+        let level = match segments.next() {
+            Some(first) => segments.fold(self.pairs.root().child(first), |level, segment| {
+                level.child(segment)
+            }),
+            None => return self.deserialize(),
+        };
+        if level.is_empty() {
+            T::deserialize(MissingDeserializer(path))
+        } else {
+            T::deserialize(LevelDeserializer(level))
+        }
+    }
+}
+
+/// Deserializer of a path that no key starts with.
+struct MissingDeserializer<'p>(&'p str);
+
+impl<'de, 'p> de::Deserializer<'de> for MissingDeserializer<'p> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Error::missing(self.0))
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_none()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
@@ -1,250 +1,399 @@
 use crate::{
-    de::{deserializer::Deserializer, level::Level},
+    de::{charset::Charset, config::DeserializerConfig, level::Pairs},
     error::{Error, Result},
 };
 
-use serde::de::Error as _;
-
 use std::borrow::Cow;
-use std::collections::BTreeMap;
-use std::iter::Iterator;
-use std::slice::Iter;
-use std::str;
-
-pub(crate) fn replace_plus(input: &[u8]) -> Cow<[u8]> {
-    match input.iter().position(|&b| b == b'+') {
-        None => Cow::Borrowed(input),
-        Some(first_position) => {
-            let mut replaced = input.to_owned();
-            replaced[first_position] = b' ';
-            for byte in &mut replaced[first_position + 1..] {
-                if *byte == b'+' {
-                    *byte = b' ';
-                }
-            }
+use std::iter::{self, Iterator};
 
-            Cow::Owned(replaced)
+/// Returns the value of a hexadecimal digit.
+fn hex_digit(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Percent-decode the bytes, and decode `+` as a space if `plus_as_space`, in a single pass.
+///
+/// The bytes are borrowed when there is nothing to decode. A `%` not followed by two hexadecimal digits
+/// is kept as it is.
+fn percent_decode(raw: &[u8], plus_as_space: bool) -> Cow<'_, [u8]> {
+    let find = |bytes: &[u8]| {
+        if plus_as_space {
+            memchr::memchr2(b'%', b'+', bytes)
+        } else {
+            memchr::memchr(b'%', bytes)
+        }
+    };
+
+    let mut decoded = Vec::new();
+    // `raw[..copied]` has been decoded into `decoded`, and the search resumes at `position`.
+    let mut copied = 0;
+    let mut position = 0;
+    while let Some(found) = find(&raw[position..]) {
+        let i = position + found;
+        let (byte, len) = match raw[i] {
+            b'+' => (b' ', 1),
+            _ => match raw.get(i + 1..i + 3) {
+                Some(&[high, low]) => match (hex_digit(high), hex_digit(low)) {
+                    (Some(high), Some(low)) => (high << 4 | low, 3),
+                    _ => {
+                        position = i + 1;
+                        continue;
+                    }
+                },
+                _ => break,
+            },
+        };
+        if decoded.is_empty() {
+            decoded.reserve(raw.len());
         }
+        decoded.extend_from_slice(&raw[copied..i]);
+        decoded.push(byte);
+        copied = i + len;
+        position = copied;
+    }
+
+    if copied == 0 {
+        Cow::Borrowed(raw)
+    } else {
+        decoded.extend_from_slice(&raw[copied..]);
+        Cow::Owned(decoded)
+    }
+}
+
+/// Decode a raw key segment or value into a string.
+pub(crate) fn decode(
+    raw: &[u8],
+    plus_as_space: bool,
+    charset: Charset,
+) -> std::result::Result<Cow<'_, str>, String> {
+    charset.decode(percent_decode(raw, plus_as_space))
+}
+
+/// Lay already decoded pairs out as a query-string, so that error positions point into it,
+/// along with the spans of their keys and values for [`Parser::parse_decoded`].
+pub(crate) fn lay_out<I, K, V>(pairs: I) -> (String, Vec<Pair>)
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let mut input = String::new();
+    let mut spans = Vec::new();
+    for (key, value) in pairs {
+        let key_start = input.len();
+        input.push_str(key.as_ref());
+        let key_end = input.len();
+        input.push('=');
+        input.push_str(value.as_ref());
+        spans.push(Pair {
+            key: Span::new(key_start, key_end),
+            value: Some(Span::new(key_end + 1, input.len())),
+        });
+        input.push('&');
+    }
+    (input, spans)
+}
+
+/// Byte range of a part of the input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+/// A raw `key=value` pair found by the [`Parser`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Pair {
+    /// The whole key, including the `.` between its segments.
+    pub key: Span,
+    /// The value after the first `=`, or `None` if the pair has no `=`.
+    pub value: Option<Span>,
+}
+
+impl Pair {
+    /// Iterate over the spans of the non-empty `.`-separated segments of the key.
+    pub fn segments(self, input: &[u8]) -> impl Iterator<Item = Span> + '_ {
+        let key = self.key;
+        let mut start = key.start;
+        memchr::memchr_iter(b'.', &input[key.start..key.end])
+            .map(move |dot| key.start + dot)
+            .chain(iter::once(key.end))
+            .map(move |end| {
+                let span = Span::new(start, end);
+                start = end + 1;
+                span
+            })
+            .filter(|span| span.len() > 0)
     }
 }
 
 /// Parse x-www-form-urlencoded string into structured key-value mappings.
 ///
-/// 1. Initial state
-///
-/// Parser starts to parse from following state
+/// The parser is an iterator over the [`Pair`]s of the input and never recurses.
+/// It moves through the following states, each scanning forward from the current position:
 ///
 /// ```plaintext
-///      k e y 1 = v a l u e 1 & k e y 2 . k e y 3 = v a l u e 3 & k e y 2 . k e y 4 = v a l u e 4
-///    ^ ^
-/// head tail
+///      k e y 1 = v a l u e 1 & k e y 2 . k e y 3 = v a l u e 3
+///      ^       ^ ^           ^ ^                 ^ ^           ^
+///      |  key  | |   value   | |       key       | |   value   |
 /// ```
 ///
-/// Parser iterates over inner iterator as below
+/// 1. At the start of a pair, the parser finds the next `&` (or the end of the input).
+///    Empty pairs such as the one in `a=1&&b=2` are skipped.
+/// 2. The key spans up to the first `=` in the pair, and the value spans from there to the end of the pair.
+///    A pair without `=` has no value.
+/// 3. The key is split into its `.`-separated segments by [`Pair::segments`], which are decoded
+///    and added to [`Pairs`] along with the span of the still encoded value.
 ///
-/// ```plaintext
-///      k e y 1 = v a l u e 1 & k e y 2 . k e y 3 = v a l u e 3 & k e y 2 . k e y 4 = v a l u e 4
-///      ^    ^
-///      tail head
-/// ```
-///
-/// 2. When parser encounter separator character such as `=`, `.`, and `&`
-///
-/// ```plaintext
-///      k e y 1 = v a l u e 1 & k e y 2 . k e y 3 = v a l u e 3 & k e y 2 . k e y 4 = v a l u e 4
-///      ^       ^
-///      tail    head
-/// ```
-///
-/// When parser encounter separator character such as `=`, `.`, and `&`, collect the str from tail to head.
-/// After the collection, tail is positioned one after head, and transit to `parse_map_value`.
-///
-/// ```plaintext
-///      k e y 1 = v a l u e 1 & k e y 2 . k e y 3 = v a l u e 3 & k e y 2 . k e y 4 = v a l u e 4
-///              ^ ^
-///           head tail
-/// ```
-///
-/// 3. Parse Map Value
-///
-/// In `parse_map_value`, check the character pointed by current head.
-///
-/// - the character is `=`, parser should parse the **value** corresponding the key.
-/// - the character is `&`, the value must be empty. Parser simply inserts empty string for the key.
-/// - the character is `.`, the value corresponding the key is structured(nested). So call `parse` method recursively.
-///
-pub struct Parser<'a> {
+/// Every scan looks for a single byte with `memchr`, and every part is recorded as a [`Span`]
+/// so that errors can point at the offending position.
+pub(crate) struct Parser<'a> {
     inner: &'a [u8],
-    iter: Iter<'a, u8>,
-    head: isize,
-    tail: usize,
+    position: usize,
+    config: DeserializerConfig,
+    charset: Charset,
 }
 
 impl<'a> Iterator for Parser<'a> {
-    type Item = &'a u8;
-    #[inline]
+    type Item = Pair;
+
     fn next(&mut self) -> Option<Self::Item> {
-        self.head += 1;
-        self.iter.next()
+        while self.position < self.inner.len() {
+            let start = self.position;
+            let rest = &self.inner[start..];
+            let end = start + memchr::memchr(b'&', rest).unwrap_or(rest.len());
+            self.position = end + 1;
+
+            if start == end {
+                continue;
+            }
+            let pair = &self.inner[start..end];
+            return Some(match memchr::memchr(b'=', pair) {
+                Some(equal) => Pair {
+                    key: Span::new(start, start + equal),
+                    value: Some(Span::new(start + equal + 1, end)),
+                },
+                None => Pair {
+                    key: Span::new(start, end),
+                    value: None,
+                },
+            });
+        }
+        None
     }
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(encoded: &'a [u8]) -> Self {
+    pub fn new(encoded: &'a [u8], config: DeserializerConfig) -> Self {
         Parser {
             inner: encoded,
-            iter: encoded.iter(),
-            head: -1, // In the initial state, the head will start at index -1 to ensure that the head is 0 when iter() is first called.
-            tail: 0,
+            position: 0,
+            config,
+            charset: config.charset,
         }
     }
 
-    /// Shrink the range from the tail to the head.
-    /// The tail will be positioned one after the head.
-    fn shrink(&mut self) {
-        self.tail = self.head as usize + 1;
+    /// Check the pair against the configured limits before decoding it.
+    fn check_pair(&self, pair: Pair) -> Result<()> {
+        if pair.key.len() > self.config.max_key_len {
+            return Err(Error::limit_exceeded("key length", self.config.max_key_len));
+        }
+        if pair.value.map_or(0, |value| value.len()) > self.config.max_value_len {
+            return Err(Error::limit_exceeded(
+                "value length",
+                self.config.max_value_len,
+            ));
+        }
+        let key = &self.inner[pair.key.start..pair.key.end];
+        if memchr::memchr_iter(b'.', key).count() >= self.config.max_depth {
+            return Err(Error::limit_exceeded("depth", self.config.max_depth));
+        }
+        Ok(())
     }
 
-    /// Collect the URL-decoded string slice from the tail to the head with decoding and shrink the tail.
-    fn collect_str(&mut self) -> Result<Cow<'a, str>> {
-        let replaced = replace_plus(&self.inner[self.tail..self.head as usize]);
-        let decoder = percent_encoding::percent_decode(&replaced);
-
-        let maybe_decoded = decoder
-            .decode_utf8()
-            .map_err(|e| Error::custom(e.to_string()))?;
+    /// Reject any syntax that the forgiving parser would silently skip or accept.
+    ///
+    /// This rejects empty pairs (`a=1&&b=2`), empty keys (`=1`), empty key segments (`.a`, `a.`, `a..b`),
+    /// a `=` inside a value (`a==1`) and malformed percent-encoding (`%zz`).
+    fn validate(&self) -> Result<()> {
+        enum State {
+            PairStart,
+            SegmentStart,
+            Key,
+            Value,
+        }
 
-        let ret: Result<Cow<'a, str>> = match maybe_decoded {
-            Cow::Borrowed(_) => match replaced {
-                Cow::Borrowed(_) => {
-                    let res = str::from_utf8(&self.inner[self.tail..self.head as usize])
-                        .map_err(|e| Error::custom(e.to_string()))?;
-                    Ok(Cow::Borrowed(res))
+        let mut state = State::PairStart;
+        for (position, &b) in self.inner.iter().enumerate() {
+            state = match (state, b) {
+                (State::PairStart, b'&') => return Err(Error::parse_error("empty pair", position)),
+                (State::PairStart, b'=') => return Err(Error::parse_error("empty key", position)),
+                (State::PairStart | State::SegmentStart, b'.' | b'=' | b'&') => {
+                    return Err(Error::parse_error("empty key segment", position))
                 }
-                Cow::Owned(owned) => {
-                    let res = String::from_utf8(owned).map_err(|e| Error::custom(e.to_string()))?;
-                    Ok(Cow::Owned(res))
+                (State::Key, b'.') => State::SegmentStart,
+                (State::Key, b'=') => State::Value,
+                (State::Key | State::Value, b'&') => State::PairStart,
+                (State::Value, b'=') => {
+                    return Err(Error::parse_error("unexpected '=' in value", position))
                 }
-            },
-            Cow::Owned(owned) => Ok(Cow::Owned(owned)),
-        };
-        self.shrink();
-        ret.map_err(Error::from)
-    }
-
-    /// Parse the entire input string into a Level struct, construct a Deserializer, and return it.
-    pub(crate) fn as_deserializer(&mut self) -> Result<Deserializer<'a>> {
-        let map = BTreeMap::default();
-        let mut root = Level::Nested(map);
+                (State::Value, _) => State::Value,
+                (_, _) => State::Key,
+            };
 
-        while self.parse(&mut root)? {}
-        let iter = match root {
-            Level::Nested(map) => map.into_iter(),
-            _ => BTreeMap::default().into_iter(),
-        };
-        Ok(Deserializer { iter, value: None })
-    }
-
-    /// The top-level parsing function. It checks the first character to determine the type of key
-    /// and call the parsing function.
-    fn parse(&mut self, node: &mut Level<'a>) -> Result<bool> {
-        // Check the first character to determine parsing type
-        match self.next() {
-            Some(x) => {
-                match *x {
-                    // Set the tail position and parse key and value, which is the child of current node.
-                    b'.' => {
-                        self.shrink();
-                        let key = self.parse_key()?;
-                        self.parse_map_value(key, node)?;
-                        return Ok(true);
-                    }
-                    // Set the tail position and continue to next.
-                    b'&' => {
-                        self.shrink();
-                        Ok(true)
-                    }
-                    // Normally parse key and value.
-                    _ => {
-                        let key = self.parse_key()?;
-                        // Root keys are _always_ map values
-                        self.parse_map_value(key, node)?;
-                        Ok(true)
-                    }
+            if b == b'%' {
+                let escape = self.inner.get(position + 1..position + 3);
+                if !escape.is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)) {
+                    return Err(Error::parse_error("malformed percent-encoding", position));
                 }
             }
-            None => Ok(false),
+        }
+
+        match state {
+            State::PairStart if !self.inner.is_empty() => {
+                Err(Error::parse_error("empty pair", self.inner.len()))
+            }
+            State::SegmentStart => Err(Error::parse_error("empty key segment", self.inner.len())),
+            _ => Ok(()),
         }
     }
 
-    /// Collect the string slice up to the separator character and return the collected key.
-    fn parse_key(&mut self) -> Result<Cow<'a, str>> {
-        loop {
-            if let Some(x) = self.next() {
-                match *x {
-                    b'.' | b'=' | b'&' => {
-                        return self.collect_str();
-                    }
-                    _ => {
-                        // for any other character
-                        // do nothing, keep adding to key
-                    }
-                }
-            } else {
-                // no more string to parse
-                return self.collect_str();
+    /// Decode a key segment of the input.
+    fn decode(&self, span: Span) -> Result<Cow<'a, str>> {
+        decode(
+            &self.inner[span.start..span.end],
+            self.config.plus_as_space,
+            self.charset,
+        )
+        .map_err(|e| Error::parse_error(e, span.start))
+    }
+
+    /// Find the `_charset_` parameter sent by HTML forms and use the charset it names.
+    #[cfg(feature = "charset")]
+    fn detect_charset(&mut self) -> Result<()> {
+        let parser = Parser::new(self.inner, self.config);
+        for pair in parser {
+            if &self.inner[pair.key.start..pair.key.end] != b"_charset_" {
+                continue;
+            }
+            if let Some(value) = pair.value {
+                let label = &self.inner[value.start..value.end];
+                self.charset = Charset::for_label(label)
+                    .ok_or_else(|| Error::parse_error("unknown charset", value.start))?;
             }
+            break;
         }
+        Ok(())
     }
 
-    /// The `(key,value)` pair is determined to be corresponding to a map entry,
-    /// so parse it as such. The first part of the `key` has been parsed.
-    fn parse_map_value(&mut self, key: Cow<'a, str>, node: &mut Level<'a>) -> Result<()> {
-        let res = loop {
-            // Check the character that current head is pointing to.
-            match self.inner[self.head as usize] {
-                b'=' => {
-                    // Key is finished, parse up until the '&' as the value.
-                    self.shrink();
-                    for _ in self.take_while(|b| *b != &b'&') {}
-                    let value: Cow<'a, str> = self.collect_str()?;
-                    node.insert_map_value(key, value);
-                    break Ok(());
-                }
-                b'&' => {
-                    // There is no value, so insert empty string.
-                    node.insert_map_value(key, Cow::Borrowed(""));
-                    break Ok(());
-                }
-                b'.' => {
-                    // The next string is a key of the nested map.
+    /// Collect pairs that are already decoded, laid out in the input as `key=value&`, into [`Pairs`] sorted by key.
+    ///
+    /// The same limits apply as to an encoded input, but nothing is percent-decoded.
+    pub(crate) fn parse_decoded(self, decoded: &[Pair]) -> Result<Pairs<'a>> {
+        let input = self.inner;
+        self.parse_decoded_by(decoded, |pairs, _, pair| {
+            for segment in pair.segments(input) {
+                let segment = std::str::from_utf8(&input[segment.start..segment.end])
+                    .map_err(|e| Error::parse_error(e, segment.start))?;
+                pairs.push_segment(Cow::Borrowed(segment));
+            }
+            Ok(())
+        })
+    }
 
-                    // If the node is uninitialized, initialize it with empty BTreeMap.
-                    if let Level::UnInitialized = *node {
-                        *node = Level::Nested(BTreeMap::default());
-                    }
-                    // if the node is the map, call the top-level parse function with the node.
-                    if let Level::Nested(ref mut map) = *node {
-                        self.shrink();
-                        let _ = self.parse(map.entry(key).or_insert(Level::UnInitialized))?;
-                        break Ok(());
-                    } else {
-                        // We expected to parse into a map here.
-                        break Err(Error::custom(format!(
-                            "tried to insert a new key into {:?}",
-                            node
-                        )));
-                    }
-                }
-                _ => {
-                    // Break the loop when the iterator is finished.
-                    if let None = self.next() {
-                        break Ok(());
-                    }
-                }
+    /// Collect pairs that are already decoded, laid out in the input as `key=value&`, into [`Pairs`] sorted by key,
+    /// with the segments of the `i`-th key given by `keys[i]` rather than split at `.`.
+    pub(crate) fn parse_decoded_segments(
+        self,
+        decoded: &[Pair],
+        keys: &'a [Vec<String>],
+    ) -> Result<Pairs<'a>> {
+        self.parse_decoded_by(decoded, |pairs, i, _| {
+            for segment in keys[i].iter().filter(|segment| !segment.is_empty()) {
+                pairs.push_segment(Cow::Borrowed(segment));
             }
-        };
+            Ok(())
+        })
+    }
+
+    fn parse_decoded_by(
+        self,
+        decoded: &[Pair],
+        mut push_segments: impl FnMut(&mut Pairs<'a>, usize, Pair) -> Result<()>,
+    ) -> Result<Pairs<'a>> {
+        if self.inner.len() > self.config.max_input_len {
+            return Err(Error::limit_exceeded(
+                "input length",
+                self.config.max_input_len,
+            ));
+        }
+        if decoded.len() > self.config.max_pairs {
+            return Err(Error::limit_exceeded(
+                "number of pairs",
+                self.config.max_pairs,
+            ));
+        }
 
-        res
+        let mut pairs = Pairs::new(self.inner, self.config, self.charset).decoded();
+        for (i, &pair) in decoded.iter().enumerate() {
+            self.check_pair(pair)?;
+            push_segments(&mut pairs, i, pair)?;
+            pairs.push_entry(pair.key, pair.value);
+        }
+        pairs.sort();
+        Ok(pairs)
+    }
+
+    /// Parse the entire input string into [`Pairs`] sorted by key.
+    pub(crate) fn parse(mut self) -> Result<Pairs<'a>> {
+        if self.inner.len() > self.config.max_input_len {
+            return Err(Error::limit_exceeded(
+                "input length",
+                self.config.max_input_len,
+            ));
+        }
+
+        if self.config.strict {
+            self.validate()?;
+        }
+
+        #[cfg(feature = "charset")]
+        if self.config.charset_parameter {
+            self.detect_charset()?;
+        }
+
+        let mut pairs = Pairs::new(self.inner, self.config, self.charset);
+        let mut count = 0;
+        while let Some(pair) = self.next() {
+            count += 1;
+            if count > self.config.max_pairs {
+                return Err(Error::limit_exceeded(
+                    "number of pairs",
+                    self.config.max_pairs,
+                ));
+            }
+            self.check_pair(pair)?;
+            for segment in pair.segments(self.inner) {
+                pairs.push_segment(self.decode(segment)?);
+            }
+            pairs.push_entry(pair.key, pair.value);
+        }
+        pairs.sort();
+        Ok(pairs)
     }
 }
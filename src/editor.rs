@@ -0,0 +1,211 @@
+use crate::{
+    de::{
+        config::DeserializerConfig,
+        parser::{self, Parser},
+    },
+    error::{Error, Result},
+    ser::{
+        self,
+        config::SerializerConfig,
+        encoder::{Encoder, Sink},
+    },
+};
+use serde::{de, Serialize};
+use std::fmt;
+
+/// Edits the pairs of a query-string by key path, leaving the other pairs as they are.
+///
+/// Pairs keep their order and their original encoding, including the ones no struct would deserialize,
+/// and set pairs are encoded with the [`SerializerConfig`]. Only empty pairs such as the one in `a=1&&b=2`
+/// are dropped.
+///
+/// ```
+/// use serde_structuredqs::QueryEditor;
+///
+/// let mut editor = QueryEditor::parse("q=caf%C3%A9&filter.category=A&page=2&utm_source=mail").unwrap();
+/// assert_eq!(editor.get::<u32>("page").unwrap(), 2);
+/// editor.set("page", &3).unwrap().remove("filter.category");
+/// assert_eq!(editor.to_string(), "q=caf%C3%A9&page=3&utm_source=mail");
+/// ```
+#[derive(Debug)]
+pub struct QueryEditor {
+    pairs: Vec<EditedPair>,
+    de_config: DeserializerConfig,
+    ser_config: SerializerConfig,
+}
+
+/// A pair as it is written in the query-string, along with its decoded key segments.
+#[derive(Debug)]
+struct EditedPair {
+    segments: Vec<String>,
+    raw: String,
+}
+
+impl EditedPair {
+    /// Returns whether the key of the pair is `path` or is nested under it.
+    fn is_under(&self, path: &[&str]) -> bool {
+        self.segments.len() >= path.len() && self.segments.iter().zip(path).all(|(a, b)| a == b)
+    }
+
+    /// Returns whether the key of the pair is a parent of `path`, whose value would conflict with its children.
+    fn is_parent_of(&self, path: &[&str]) -> bool {
+        !self.segments.is_empty()
+            && self.segments.len() < path.len()
+            && self.segments.iter().zip(path).all(|(a, b)| a == b)
+    }
+}
+
+/// Collects serialized pairs, encoded with the configuration of the editor.
+struct Collector {
+    pairs: Vec<EditedPair>,
+    config: SerializerConfig,
+}
+
+impl Collector {
+    fn push(&mut self, key: &str, raw: String) {
+        let segments = key
+            .split('.')
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_owned)
+            .collect();
+        self.pairs.push(EditedPair { segments, raw });
+    }
+}
+
+impl Sink for Collector {
+    fn append_pair(&mut self, key: &str, value: &str) -> &mut Self {
+        let mut raw = String::new();
+        Encoder::new(&mut raw, self.config).append_pair(key, value);
+        self.push(key, raw);
+        self
+    }
+
+    fn append_key_only(&mut self, key: &str) -> &mut Self {
+        let mut raw = String::new();
+        Encoder::new(&mut raw, self.config).append_key_only(key);
+        self.push(key, raw);
+        self
+    }
+}
+
+fn split_path(path: &str) -> Vec<&str> {
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+impl QueryEditor {
+    /// Parse query-string to edit it, decoding its keys with `de_config` and encoding set values with `ser_config`.
+    ///
+    /// The input is checked as by [`DeserializerConfig::deserialize_str`], including its limits.
+    pub fn new(
+        input: &str,
+        de_config: DeserializerConfig,
+        ser_config: SerializerConfig,
+    ) -> Result<Self> {
+        let bytes = input.as_bytes();
+        Parser::new(bytes, de_config).parse()?;
+
+        let mut pairs = Vec::new();
+        for pair in Parser::new(bytes, de_config) {
+            let segments = pair
+                .segments(bytes)
+                .map(|span| {
+                    parser::decode(
+                        &bytes[span.start..span.end],
+                        de_config.plus_as_space,
+                        de_config.charset,
+                    )
+                    .map(|segment| segment.into_owned())
+                    .map_err(|e| Error::parse_error(e, span.start))
+                })
+                .collect::<Result<_>>()?;
+            let end = pair.value.map_or(pair.key.end, |value| value.end);
+            pairs.push(EditedPair {
+                segments,
+                raw: input[pair.key.start..end].to_owned(),
+            });
+        }
+        Ok(Self {
+            pairs,
+            de_config,
+            ser_config,
+        })
+    }
+
+    /// Parse query-string to edit it with the default configurations.
+    pub fn parse(input: &str) -> Result<Self> {
+        Self::new(
+            input,
+            DeserializerConfig::default(),
+            SerializerConfig::default(),
+        )
+    }
+
+    /// Deserialize the keys nested under `path` as they are now. See [`ParsedQuery::deserialize_at`](crate::ParsedQuery::deserialize_at).
+    pub fn get<T: de::DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.de_config.deserialize_str_at(path, &self.to_string())
+    }
+
+    /// Replace the pairs at or under `path` with the pairs of `value`, serialized under `path`.
+    ///
+    /// A pair of a parent key of `path` is replaced as well, since a key cannot have both a value and nested keys.
+    /// The new pairs take the place of the first replaced one, or are appended if there was none.
+    /// A `path` without any segment, such as `""`, fails rather than replacing the whole query.
+    pub fn set<T: Serialize>(&mut self, path: &str, value: &T) -> Result<&mut Self> {
+        let new = self.serialize(path, value)?;
+        let path = split_path(path);
+        let position = self
+            .pairs
+            .iter()
+            .position(|pair| pair.is_under(&path) || pair.is_parent_of(&path));
+        self.pairs
+            .retain(|pair| !pair.is_under(&path) && !pair.is_parent_of(&path));
+        let position = position.unwrap_or(self.pairs.len());
+        self.pairs.splice(position..position, new);
+        Ok(self)
+    }
+
+    /// Append the pairs of `value`, serialized under `path`, after all the other pairs.
+    /// A `path` without any segment fails.
+    pub fn append<T: Serialize>(&mut self, path: &str, value: &T) -> Result<&mut Self> {
+        let new = self.serialize(path, value)?;
+        self.pairs.extend(new);
+        Ok(self)
+    }
+
+    /// Remove the pairs at or under `path`. A `path` without any segment removes nothing.
+    pub fn remove(&mut self, path: &str) -> &mut Self {
+        let path = split_path(path);
+        if path.is_empty() {
+            return self;
+        }
+        self.pairs.retain(|pair| !pair.is_under(&path));
+        self
+    }
+
+    fn serialize<T: Serialize>(&self, path: &str, value: &T) -> Result<Vec<EditedPair>> {
+        if split_path(path).is_empty() {
+            return Err(Error::Custom(format!("empty path: \"{}\"", path)));
+        }
+        let mut collector = Collector {
+            pairs: Vec::new(),
+            config: self.ser_config,
+        };
+        ser::serialize_at(&mut collector, path, value, self.ser_config)?;
+        Ok(collector.pairs)
+    }
+}
+
+/// Renders the edited query-string.
+impl fmt::Display for QueryEditor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, pair) in self.pairs.iter().enumerate() {
+            if i > 0 {
+                f.write_str("&")?;
+            }
+            f.write_str(&pair.raw)?;
+        }
+        Ok(())
+    }
+}
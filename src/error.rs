@@ -11,6 +11,12 @@ pub enum Error {
     Custom(String),
     #[error("failed to parse with error: '{0}' at position: {1}")]
     Parse(String, usize),
+    #[error("limit exceeded: {0} must be at most {1}")]
+    LimitExceeded(&'static str, usize),
+    #[error("missing key: \"{0}\"")]
+    Missing(String),
+    #[error("duplicate key: \"{0}\"")]
+    Duplicate(String),
     #[error("unsupported type for serialization")]
     Unsupported,
     #[error(transparent)]
@@ -34,6 +40,14 @@ impl Error {
     {
         Error::Parse(msg.to_string(), position)
     }
+
+    pub fn limit_exceeded(limit: &'static str, max: usize) -> Self {
+        Error::LimitExceeded(limit, max)
+    }
+
+    pub fn missing(key: &str) -> Self {
+        Error::Missing(key.to_owned())
+    }
 }
 
 impl ser::Error for Error {
@@ -91,6 +91,26 @@
 //! )
 //! ```
 //!
+//! Separators are percent-encoded by default. `SerializerConfig::encoding` selects another encoding,
+//! such as `Encoding::Readable` which leaves `,` `:` and `/` as they are.
+//!
+//! ```rust
+//! use serde::Serialize;
+//! use serde_structuredqs::{Encoding, SerializerConfig};
+//!
+//! #[derive(Serialize)]
+//! struct MyStruct {
+//!     key: Vec<i32>,
+//! }
+//! assert_eq!(
+//!     SerializerConfig::new()
+//!         .encoding(Encoding::Readable)
+//!         .to_string(&MyStruct { key: vec![100, 200] })
+//!         .unwrap(),
+//!     String::from("key=100,200")
+//! )
+//! ```
+//!
 //! Similarly, comma-separated values are deserialized as `Vec`.
 //!
 //! ```rust
@@ -116,11 +136,73 @@
 //!     }
 //! )
 //! ```
+//!
+//! An element containing a separator or `\` is escaped with `\`, an empty string element is written as `\e`,
+//! a `None` element as `\0`, and an empty `Vec` as a single `,`, so every sequence round-trips exactly.
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+//! struct MyStruct {
+//!     key: Vec<Option<String>>,
+//! }
+//!
+//! let value = MyStruct {
+//!     key: vec![Some(String::from("a,b")), None, Some(String::from(""))],
+//! };
+//! let encoded = serde_structuredqs::to_string(&value).unwrap();
+//! assert_eq!(encoded, "key=a%5C%2Cb%2C%5C0%2C%5Ce");
+//! assert_eq!(serde_structuredqs::from_str::<MyStruct>(&encoded).unwrap(), value);
+//! ```
+//!
+//! Nested sequences and tuples are joined with a hierarchy of separators, `,` `;` `|` by default,
+//! which can be changed with `SerializerConfig::separators` and `DeserializerConfig::separators`.
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+//! struct MyStruct {
+//!     matrix: Vec<Vec<i32>>,
+//! }
+//!
+//! assert_eq!(
+//!     serde_structuredqs::from_str::<MyStruct>("matrix=1,2;3,4").unwrap(),
+//!     MyStruct { matrix: vec![vec![1, 2], vec![3, 4]] }
+//! );
+//! ```
+//!
+//! ### Charsets
+//!
+//! Keys and values are decoded as UTF-8 by default; `Charset::Utf8Lossy` replaces invalid sequences instead of failing.
+//! With the `charset` feature, `DeserializerConfig::charset` and `SerializerConfig::charset` accept any encoding of
+//! [encoding_rs](https://crates.io/crates/encoding_rs), such as Shift_JIS, EUC-JP or Latin-1,
+//! and `DeserializerConfig::charset_parameter` honours the `_charset_` parameter sent by HTML forms.
 
 mod de;
+mod editor;
 mod error;
+mod merge;
+mod sequence;
 mod ser;
 
-pub use de::{from_bytes, from_str};
+pub use de::{
+    charset::Charset, config::DeserializerConfig, from_bytes, from_pairs, from_reader, from_str,
+    from_str_at, from_str_in_place, incremental::IncrementalParser, key::KeyMatching,
+    parsed::ParsedQuery,
+};
+pub use editor::QueryEditor;
 pub use error::{Error, Result};
-pub use ser::to_string;
+pub use merge::{merge, MergeConfig, ScalarMerge, SequenceMerge};
+pub use sequence::Separators;
+pub use ser::{
+    append_to_string, append_to_url, append_to_vec, builder::QueryBuilder, canonicalize,
+    config::SerializerConfig, encoder::Encoding, to_pairs, to_serializer, to_string,
+    to_string_diff, to_vec, to_writer,
+};
+
+pub use form_urlencoded;
+
+#[cfg(feature = "charset")]
+pub use encoding_rs;
@@ -0,0 +1,333 @@
+use crate::{
+    de::{
+        config::DeserializerConfig,
+        level::{Kind, Level, LevelDeserializer, Pairs},
+        parser::{self, Parser},
+    },
+    error::{Error, Result},
+    sequence::Separators,
+    ser::{config::SerializerConfig, encoder::Encoder},
+};
+use serde::{de, Serialize};
+use std::{borrow::Cow, cmp::Ordering};
+
+/// The decoded segments of a key, and its decoded value.
+type MergedPair = (Vec<String>, String);
+
+/// How a value is merged with a value of the same key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScalarMerge {
+    /// The overlay value replaces the base value.
+    #[default]
+    Override,
+    /// The base value is kept.
+    Keep,
+}
+
+/// How a sequence value is merged with a value of the same key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SequenceMerge {
+    /// The overlay sequence replaces the base sequence.
+    #[default]
+    Replace,
+    /// The overlay elements follow the base elements.
+    Append,
+    /// The overlay elements that are not in the base sequence follow the base elements.
+    Union,
+}
+
+/// Options for merging an overlay query-string, or value, into a base query-string.
+///
+/// Keys are merged level by level: keys of only one side are kept, nested keys are merged recursively,
+/// and values of the same key are merged by the rules of [`ScalarMerge`] and [`SequenceMerge`].
+/// A value containing a separator is a sequence, as when it is deserialized,
+/// so a sequence of a single scalar is merged as a scalar. A key that has a value on one side
+/// and nested keys on the other follows the scalar rule as a whole.
+///
+/// The merged pairs are sorted by key and encoded with the [`SerializerConfig`].
+///
+/// ```
+/// use serde_structuredqs::{MergeConfig, SequenceMerge};
+///
+/// let defaults = "sort=date&page.size=20&tags=a,b";
+/// let overrides = "page.size=50&page.number=2&tags=b,c";
+/// assert_eq!(
+///     serde_structuredqs::merge(defaults, overrides).unwrap(),
+///     "page.number=2&page.size=50&sort=date&tags=b%2Cc"
+/// );
+/// assert_eq!(
+///     MergeConfig::new()
+///         .sequences(SequenceMerge::Union)
+///         .merge_str(defaults, overrides)
+///         .unwrap(),
+///     "page.number=2&page.size=50&sort=date&tags=a%2Cb%2Cc"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MergeConfig {
+    scalars: ScalarMerge,
+    sequences: SequenceMerge,
+    de_config: DeserializerConfig,
+    ser_config: SerializerConfig,
+}
+
+impl MergeConfig {
+    /// Returns the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how values of the same key are merged.
+    pub fn scalars(mut self, scalars: ScalarMerge) -> Self {
+        self.scalars = scalars;
+        self
+    }
+
+    /// Set how sequences of the same key are merged.
+    pub fn sequences(mut self, sequences: SequenceMerge) -> Self {
+        self.sequences = sequences;
+        self
+    }
+
+    /// Set how the query-strings are parsed, including the separators that sequences are split on.
+    pub fn deserializer_config(mut self, de_config: DeserializerConfig) -> Self {
+        self.de_config = de_config;
+        self
+    }
+
+    /// Set how the merged query-string, and an overlay value, are serialized.
+    pub fn serializer_config(mut self, ser_config: SerializerConfig) -> Self {
+        self.ser_config = ser_config;
+        self
+    }
+
+    /// Merge the `overlay` query-string into the `base` query-string.
+    pub fn merge_str(&self, base: &str, overlay: &str) -> Result<String> {
+        let base = self.parse(base)?;
+        let overlay = self.parse(overlay)?;
+        self.merge_pairs(&base, &overlay)
+            .map(|pairs| self.encode(pairs))
+    }
+
+    /// Merge the pairs of `overlay` into the `base` query-string.
+    pub fn merge_value<T: Serialize>(&self, base: &str, overlay: &T) -> Result<String> {
+        self.merge_value_pairs(base, overlay)
+            .map(|pairs| self.encode(pairs))
+    }
+
+    /// Merge the `overlay` query-string into the `base` query-string, and deserialize the result.
+    pub fn merge_str_into<T: de::DeserializeOwned>(&self, base: &str, overlay: &str) -> Result<T> {
+        let base = self.parse(base)?;
+        let overlay = self.parse(overlay)?;
+        let pairs = self.merge_pairs(&base, &overlay)?;
+        self.deserialize(pairs)
+    }
+
+    /// Merge the pairs of `overlay` into the `base` query-string, and deserialize the result.
+    ///
+    /// ```
+    /// use serde::{Deserialize, Serialize};
+    /// use serde_structuredqs::{MergeConfig, SequenceMerge};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Overrides {
+    ///     tags: Vec<String>,
+    /// }
+    ///
+    /// #[derive(Debug, Deserialize, Eq, PartialEq)]
+    /// struct Search {
+    ///     sort: String,
+    ///     tags: Vec<String>,
+    /// }
+    ///
+    /// let config = MergeConfig::new().sequences(SequenceMerge::Append);
+    /// let overrides = Overrides { tags: vec![String::from("c")] };
+    /// assert_eq!(
+    ///     config.merge_value_into::<Search, _>("sort=date&tags=a,b", &overrides).unwrap(),
+    ///     Search {
+    ///         sort: String::from("date"),
+    ///         tags: vec![String::from("a"), String::from("b"), String::from("c")],
+    ///     }
+    /// );
+    /// ```
+    pub fn merge_value_into<T: de::DeserializeOwned, U: Serialize>(
+        &self,
+        base: &str,
+        overlay: &U,
+    ) -> Result<T> {
+        let pairs = self.merge_value_pairs(base, overlay)?;
+        self.deserialize(pairs)
+    }
+
+    fn merge_value_pairs<T: Serialize>(&self, base: &str, overlay: &T) -> Result<Vec<MergedPair>> {
+        let base = self.parse(base)?;
+        let (input, spans) = parser::lay_out(self.ser_config.to_pairs(overlay)?);
+        let overlay = Parser::new(input.as_bytes(), self.de_config).parse_decoded(&spans)?;
+        self.merge_pairs(&base, &overlay)
+    }
+
+    fn parse<'a>(&self, input: &'a str) -> Result<Pairs<'a>> {
+        Parser::new(input.as_bytes(), self.de_config).parse()
+    }
+
+    fn encode(&self, pairs: Vec<MergedPair>) -> String {
+        let mut output = String::new();
+        let mut encoder = Encoder::new(&mut output, self.ser_config);
+        for (segments, value) in &pairs {
+            encoder.append_segments(segments.iter().map(String::as_str), value);
+        }
+        output
+    }
+
+    /// Deserialize the merged pairs, keeping the segments of their keys as they are.
+    fn deserialize<T: de::DeserializeOwned>(&self, pairs: Vec<MergedPair>) -> Result<T> {
+        let (input, spans) = parser::lay_out(
+            pairs
+                .iter()
+                .map(|(segments, value)| (segments.join("."), value)),
+        );
+        let keys: Vec<Vec<String>> = pairs.into_iter().map(|(segments, _)| segments).collect();
+        let pairs =
+            Parser::new(input.as_bytes(), self.de_config).parse_decoded_segments(&spans, &keys)?;
+        T::deserialize(LevelDeserializer(pairs.root()))
+    }
+
+    /// Merge the pairs into decoded pairs sorted by key.
+    fn merge_pairs(&self, base: &Pairs<'_>, overlay: &Pairs<'_>) -> Result<Vec<MergedPair>> {
+        let mut merged = Vec::new();
+        self.merge_levels(base.root(), overlay.root(), &mut Vec::new(), &mut merged)?;
+        Ok(merged)
+    }
+
+    fn merge_levels(
+        &self,
+        base: Level<'_, '_>,
+        overlay: Level<'_, '_>,
+        key: &mut Vec<String>,
+        merged: &mut Vec<MergedPair>,
+    ) -> Result<()> {
+        match (base.kind(), overlay.kind()) {
+            (Kind::Invalid(e, position), _) | (_, Kind::Invalid(e, position)) => {
+                Err(Error::parse_error(e, position))
+            }
+            (Kind::Flat(base_span), Kind::Flat(overlay_span)) => {
+                let value =
+                    self.merge_values(&base.decode(base_span)?, &overlay.decode(overlay_span)?);
+                merged.push((key.clone(), value));
+                Ok(())
+            }
+            (Kind::Nested, Kind::Nested) => {
+                let mut base = base.children().peekable();
+                let mut overlay = overlay.children().peekable();
+                while let Some(order) = next_order(base.peek(), overlay.peek()) {
+                    match order {
+                        Ordering::Less => {
+                            let (segment, base) = base.next().unwrap();
+                            with_segment(key, segment, |key| push_level(base, key, merged))?;
+                        }
+                        Ordering::Greater => {
+                            let (segment, overlay) = overlay.next().unwrap();
+                            with_segment(key, segment, |key| push_level(overlay, key, merged))?;
+                        }
+                        Ordering::Equal => {
+                            let (segment, base) = base.next().unwrap();
+                            let (_, overlay) = overlay.next().unwrap();
+                            with_segment(key, segment, |key| {
+                                self.merge_levels(base, overlay, key, merged)
+                            })?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+            _ => match self.scalars {
+                ScalarMerge::Override => push_level(overlay, key, merged),
+                ScalarMerge::Keep => push_level(base, key, merged),
+            },
+        }
+    }
+
+    fn merge_values(&self, base: &str, overlay: &str) -> String {
+        let separators = self.de_config.separators;
+        let level = match (separators.level(base), separators.level(overlay)) {
+            (None, None) => {
+                return match self.scalars {
+                    ScalarMerge::Override => overlay.to_owned(),
+                    ScalarMerge::Keep => base.to_owned(),
+                }
+            }
+            (base_level, overlay_level) => base_level.max(overlay_level).unwrap_or(0),
+        };
+
+        let mut elements: Vec<&str> = match self.sequences {
+            SequenceMerge::Replace => return overlay.to_owned(),
+            _ => separators.split_at_level(base, level).collect(),
+        };
+        for element in separators.split_at_level(overlay, level) {
+            if self.sequences == SequenceMerge::Append || !elements.contains(&element) {
+                elements.push(element);
+            }
+        }
+        join(separators, level, &elements)
+    }
+}
+
+/// Returns whether the next child of the base level comes before, with or after the next child of the overlay level.
+fn next_order(
+    base: Option<&(&Cow<'_, str>, Level<'_, '_>)>,
+    overlay: Option<&(&Cow<'_, str>, Level<'_, '_>)>,
+) -> Option<Ordering> {
+    match (base, overlay) {
+        (Some((base, _)), Some((overlay, _))) => Some(base.cmp(overlay)),
+        (Some(_), None) => Some(Ordering::Less),
+        (None, Some(_)) => Some(Ordering::Greater),
+        (None, None) => None,
+    }
+}
+
+/// Run `f` with `segment` appended to the key path.
+fn with_segment<T>(
+    key: &mut Vec<String>,
+    segment: &str,
+    f: impl FnOnce(&mut Vec<String>) -> T,
+) -> T {
+    key.push(segment.to_owned());
+    let result = f(key);
+    key.pop();
+    result
+}
+
+/// Push all the pairs of a level as they are.
+fn push_level(
+    level: Level<'_, '_>,
+    key: &mut Vec<String>,
+    merged: &mut Vec<MergedPair>,
+) -> Result<()> {
+    match level.kind() {
+        Kind::Invalid(e, position) => Err(Error::parse_error(e, position)),
+        Kind::Flat(span) => {
+            merged.push((key.clone(), level.decode(span)?.into_owned()));
+            Ok(())
+        }
+        Kind::Nested => level.children().try_for_each(|(segment, child)| {
+            with_segment(key, segment, |key| push_level(child, key, merged))
+        }),
+    }
+}
+
+/// Join raw elements with the separator of `level`, as the serializer writes sequences.
+fn join(separators: Separators, level: usize, elements: &[&str]) -> String {
+    let separator = separators.get(level).unwrap_or(',');
+    match elements {
+        [] => separators.empty_sequence().to_owned(),
+        // A sequence of sequences with a single element is followed by its own separator.
+        [element] if level > 0 => format!("{}{}", element, separator),
+        _ => elements.join(separator.encode_utf8(&mut [0; 4])),
+    }
+}
+
+/// Merge the `overlay` query-string into the `base` query-string with the default configuration.
+/// See [`MergeConfig`].
+pub fn merge(base: &str, overlay: &str) -> Result<String> {
+    MergeConfig::default().merge_str(base, overlay)
+}
@@ -0,0 +1,210 @@
+//! Textual representation of sequence values shared by the serializer and the deserializer.
+//!
+//! A sequence is written as a single value whose elements are joined with a separator.
+//! Nested sequences use a hierarchy of separators, `,` `;` `|` by default: the innermost
+//! sequences are joined with the first separator, the sequences containing them with the second,
+//! and so on, so `vec![vec![1, 2], vec![3, 4]]` is written as `1,2;3,4`.
+//!
+//! - A separator or `\` inside an element is escaped with a preceding `\`.
+//! - An element that is an empty string is written as `\e`.
+//! - An element that is `None` or `()` is written as `\0`.
+//! - A sequence without elements is written as the first separator alone.
+//! - A sequence of sequences with a single element is followed by its own separator, as in `1,2;`.
+//!
+//! When reading, a value is split on the highest separator it contains. Empty pieces between
+//! separators carry no element, so `,,a,,b,` is read as `["a", "b"]`.
+
+use crate::error::{Error, Result};
+
+use serde::de::Error as _;
+
+use std::borrow::Cow;
+
+/// Character that escapes a separator or itself inside an element.
+pub(crate) const ESCAPE: char = '\\';
+
+/// Element standing for an empty string.
+pub(crate) const EMPTY: &str = "\\e";
+
+/// Element standing for `None` or `()`.
+pub(crate) const NONE: &str = "\\0";
+
+const MAX_SEPARATORS: usize = 8;
+
+/// Hierarchy of characters separating the elements of nested sequences.
+///
+/// The first separator joins the elements of the innermost sequences, the next one joins
+/// sequences of those, and so on. The default hierarchy is `,` `;` `|`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Separators {
+    bytes: [u8; MAX_SEPARATORS],
+    len: usize,
+}
+
+impl Separators {
+    /// Create a hierarchy from the innermost separator to the outermost one.
+    ///
+    /// Separators must be distinct ASCII punctuation characters other than `\`,
+    /// and at most eight levels are supported.
+    pub fn new(separators: &[char]) -> Result<Self> {
+        if separators.is_empty() || separators.len() > MAX_SEPARATORS {
+            return Err(Error::custom(format!(
+                "expected 1 to {} separators, got {}",
+                MAX_SEPARATORS,
+                separators.len()
+            )));
+        }
+
+        let mut bytes = [0; MAX_SEPARATORS];
+        for (i, &c) in separators.iter().enumerate() {
+            if !c.is_ascii_punctuation() || c == ESCAPE {
+                return Err(Error::custom(format!("invalid separator: {:?}", c)));
+            }
+            if separators[..i].contains(&c) {
+                return Err(Error::custom(format!("duplicated separator: {:?}", c)));
+            }
+            bytes[i] = c as u8;
+        }
+
+        Ok(Self {
+            bytes,
+            len: separators.len(),
+        })
+    }
+
+    /// Returns the separator used for sequences nested `level` times, if any.
+    pub(crate) fn get(&self, level: usize) -> Option<char> {
+        self.as_bytes().get(level).map(|&b| b as char)
+    }
+
+    /// Returns the separator of sequences without nested sequences.
+    pub(crate) fn innermost(&self) -> &str {
+        // Separators are ASCII, so a single byte is always a valid `str`.
+        std::str::from_utf8(&self.bytes[..1]).unwrap()
+    }
+
+    /// Returns the value standing for a sequence without elements.
+    pub(crate) fn empty_sequence(&self) -> &str {
+        self.innermost()
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    fn contains(&self, c: char) -> bool {
+        c.is_ascii() && self.as_bytes().contains(&(c as u8))
+    }
+
+    /// Escape an element into `output` so that it can be joined with other elements.
+    pub(crate) fn escape_into(&self, output: &mut String, element: &str) {
+        if element.is_empty() {
+            output.push_str(EMPTY);
+            return;
+        }
+
+        let mut rest = element;
+        while let Some(position) = rest.find(|c| c == ESCAPE || self.contains(c)) {
+            output.push_str(&rest[..position]);
+            output.push(ESCAPE);
+            // Escaped characters are ASCII, so the next one is a single byte.
+            output.push_str(&rest[position..position + 1]);
+            rest = &rest[position + 1..];
+        }
+        output.push_str(rest);
+    }
+
+    /// Split a raw sequence value on the highest separator it contains,
+    /// yielding the still escaped elements and skipping empty pieces.
+    pub(crate) fn split<'a>(&self, raw: &'a str) -> impl Iterator<Item = &'a str> {
+        self.split_at_level(raw, self.level(raw).unwrap_or(0))
+    }
+
+    /// Returns the level of the highest separator in a raw value, or `None` if it contains none.
+    pub(crate) fn level(&self, raw: &str) -> Option<usize> {
+        unescaped(raw)
+            .filter_map(|(_, b)| self.as_bytes().iter().position(|&s| s == b))
+            .max()
+    }
+
+    /// Split a raw sequence value on the separator of `level`, skipping empty pieces.
+    pub(crate) fn split_at_level<'a>(
+        &self,
+        raw: &'a str,
+        level: usize,
+    ) -> impl Iterator<Item = &'a str> {
+        let separator = self.bytes[level];
+
+        Split {
+            rest: Some(raw),
+            separator,
+        }
+        .filter(|piece| !piece.is_empty())
+    }
+}
+
+impl Default for Separators {
+    fn default() -> Self {
+        Self {
+            bytes: [b',', b';', b'|', 0, 0, 0, 0, 0],
+            len: 3,
+        }
+    }
+}
+
+/// Resolve the escape sequences of an element split out by [`Separators::split`].
+///
+/// Markers such as [`EMPTY`] and [`NONE`] are not resolved here.
+pub(crate) fn unescape(element: &str) -> Cow<'_, str> {
+    if !element.contains(ESCAPE) {
+        return Cow::Borrowed(element);
+    }
+
+    let mut unescaped = String::with_capacity(element.len());
+    let mut chars = element.chars();
+    while let Some(c) = chars.next() {
+        if c == ESCAPE {
+            unescaped.push(chars.next().unwrap_or(ESCAPE));
+        } else {
+            unescaped.push(c);
+        }
+    }
+    Cow::Owned(unescaped)
+}
+
+/// Iterate over the bytes of `raw` that are not escaped, with their positions.
+fn unescaped(raw: &str) -> impl Iterator<Item = (usize, u8)> + '_ {
+    let mut escaped = false;
+    raw.bytes().enumerate().filter(move |&(_, b)| {
+        if escaped {
+            escaped = false;
+            false
+        } else {
+            escaped = b == ESCAPE as u8;
+            !escaped
+        }
+    })
+}
+
+struct Split<'a> {
+    rest: Option<&'a str>,
+    separator: u8,
+}
+
+impl<'a> Iterator for Split<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest?;
+        match unescaped(rest).find(|&(_, b)| b == self.separator) {
+            Some((position, _)) => {
+                self.rest = Some(&rest[position + 1..]);
+                Some(&rest[..position])
+            }
+            None => {
+                self.rest = None;
+                Some(rest)
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{
+    error::{Error, Result},
+    ser::{
+        self,
+        config::SerializerConfig,
+        encoder::{Encoder, Sink},
+    },
+};
+use serde::Serialize;
+use std::{collections::BTreeSet, ops::Bound};
+
+/// Builds one query-string out of several values and raw pairs.
+///
+/// Every value is serialized with the same configuration, at the top level or under a key prefix.
+/// A key that was already written, or that is the parent or a child of one, fails with
+/// [`Error::Duplicate`], since the query-string could not be deserialized back.
+/// A failed append leaves the query-string as it was.
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_structuredqs::{QueryBuilder, SerializerConfig};
+///
+/// #[derive(Serialize)]
+/// struct Filters {
+///     category: String,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Pagination {
+///     page: u32,
+/// }
+///
+/// let mut builder = QueryBuilder::new(SerializerConfig::new());
+/// builder
+///     .append_at("f", &Filters { category: String::from("A") })?
+///     .append(&Pagination { page: 2 })?
+///     .append_pair("lang", "en")?;
+/// assert_eq!(builder.finish(), "f.category=A&page=2&lang=en");
+/// # Ok::<(), serde_structuredqs::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct QueryBuilder {
+    output: String,
+    keys: BTreeSet<String>,
+    config: SerializerConfig,
+}
+
+impl QueryBuilder {
+    pub fn new(config: SerializerConfig) -> Self {
+        Self {
+            output: String::new(),
+            keys: BTreeSet::new(),
+            config,
+        }
+    }
+
+    /// Append the pairs of a struct.
+    pub fn append<T>(&mut self, value: &T) -> Result<&mut Self>
+    where
+        T: Serialize,
+    {
+        self.append_at("", value)
+    }
+
+    /// Append the pairs of a value under `prefix`: a struct as nested keys,
+    /// or any other value as the value of `prefix` itself.
+    pub fn append_at<T>(&mut self, prefix: &str, value: &T) -> Result<&mut Self>
+    where
+        T: Serialize,
+    {
+        let config = self.config;
+        self.write(|sink| ser::serialize_at(sink, prefix, value, config))
+    }
+
+    /// Append a single pair, whose key is written as it is rather than split into nested keys.
+    pub fn append_pair(&mut self, key: &str, value: &str) -> Result<&mut Self> {
+        self.write(|sink| {
+            sink.append_pair(key, value);
+            Ok(())
+        })
+    }
+
+    /// Returns the query-string built so far.
+    pub fn as_str(&self) -> &str {
+        &self.output
+    }
+
+    pub fn finish(self) -> String {
+        self.output
+    }
+
+    /// Write pairs with `f`, and keep them only if they were written without error or duplicate key.
+    fn write<F>(&mut self, f: F) -> Result<&mut Self>
+    where
+        F: FnOnce(&mut Checked<'_>) -> Result<()>,
+    {
+        let len = self.output.len();
+        let leading = (!self.output.is_empty()).then_some('&');
+        let mut sink = Checked {
+            encoder: Encoder::new(&mut self.output, self.config).leading(leading),
+            keys: &self.keys,
+            added: BTreeSet::new(),
+            duplicate: None,
+        };
+        let result = f(&mut sink);
+        let Checked {
+            added, duplicate, ..
+        } = sink;
+        match result.and(duplicate.map_or(Ok(()), |key| Err(Error::Duplicate(key)))) {
+            Ok(()) => {
+                self.keys.extend(added);
+                Ok(self)
+            }
+            Err(e) => {
+                self.output.truncate(len);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Returns whether `key` is in `keys`, or is the parent or a child of one of them.
+fn conflicts(keys: &BTreeSet<String>, key: &str) -> bool {
+    if keys.contains(key) {
+        return true;
+    }
+    if key
+        .match_indices('.')
+        .any(|(i, _)| keys.contains(&key[..i]))
+    {
+        return true;
+    }
+    let children = format!("{}.", key);
+    keys.range::<str, _>((Bound::Included(children.as_str()), Bound::Unbounded))
+        .next()
+        .is_some_and(|child| child.starts_with(&children))
+}
+
+/// Writes pairs into the output of a [`QueryBuilder`], recording their keys and the first duplicate one.
+struct Checked<'b> {
+    encoder: Encoder<&'b mut String>,
+    keys: &'b BTreeSet<String>,
+    added: BTreeSet<String>,
+    duplicate: Option<String>,
+}
+
+impl<'b> Checked<'b> {
+    fn check(&mut self, key: &str) {
+        if self.duplicate.is_none() && (conflicts(self.keys, key) || conflicts(&self.added, key)) {
+            self.duplicate = Some(key.to_owned());
+        }
+        self.added.insert(key.to_owned());
+    }
+}
+
+impl<'b> Sink for Checked<'b> {
+    fn append_pair(&mut self, key: &str, value: &str) -> &mut Self {
+        self.check(key);
+        self.encoder.append_pair(key, value);
+        self
+    }
+
+    fn append_key_only(&mut self, key: &str) -> &mut Self {
+        self.check(key);
+        self.encoder.append_key_only(key);
+        self
+    }
+}
@@ -0,0 +1,505 @@
+use crate::{
+    de::{config::DeserializerConfig, parser::Parser},
+    error::Result,
+    sequence::Separators,
+    ser::{
+        self,
+        diff::{Defaults, Diff},
+        encoder::{Encoder, Encoding, Sink, Writer},
+        toplevel::{Buffers, TopLevelSerializer},
+    },
+};
+use form_urlencoded::Target;
+use serde::Serialize;
+use std::io;
+
+/// Options for serializing values into query-strings.
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_structuredqs::{SerializerConfig, Separators};
+///
+/// #[derive(Serialize)]
+/// struct Polygon {
+///     points: Vec<(i32, i32)>,
+/// }
+///
+/// let config = SerializerConfig::new().separators(Separators::new(&['~', '!']).unwrap());
+/// assert_eq!(
+///     config.to_string(&Polygon { points: vec![(0, 0), (3, 0), (0, 4)] }).unwrap(),
+///     "points=0%7E0%213%7E0%210%7E4"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SerializerConfig {
+    pub(crate) separators: Separators,
+    pub(crate) encoding: Encoding,
+    pub(crate) canonical: bool,
+    #[cfg(feature = "charset")]
+    pub(crate) charset: Option<&'static encoding_rs::Encoding>,
+}
+
+impl SerializerConfig {
+    /// Returns the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the hierarchy of separators used to join nested sequences.
+    pub fn separators(mut self, separators: Separators) -> Self {
+        self.separators = separators;
+        self
+    }
+
+    /// Set how keys and values are percent-encoded.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_structuredqs::{Encoding, SerializerConfig};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     q: String,
+    ///     ids: Vec<u32>,
+    /// }
+    ///
+    /// let params = Params { q: String::from("a b/c"), ids: vec![1, 2] };
+    /// assert_eq!(
+    ///     SerializerConfig::new().to_string(&params).unwrap(),
+    ///     "q=a+b%2Fc&ids=1%2C2"
+    /// );
+    /// assert_eq!(
+    ///     SerializerConfig::new().encoding(Encoding::Rfc3986).to_string(&params).unwrap(),
+    ///     "q=a%20b%2Fc&ids=1%2C2"
+    /// );
+    /// assert_eq!(
+    ///     SerializerConfig::new().encoding(Encoding::Readable).to_string(&params).unwrap(),
+    ///     "q=a%20b/c&ids=1,2"
+    /// );
+    /// ```
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Set the charset keys and values are encoded in before percent-encoding. It is UTF-8 by default.
+    ///
+    /// Characters the charset cannot represent are written as HTML numeric character references
+    /// such as `&#128512;`, as browsers do when submitting forms.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_structuredqs::{encoding_rs, SerializerConfig};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     name: String,
+    /// }
+    ///
+    /// let config = SerializerConfig::new().charset(encoding_rs::SHIFT_JIS);
+    /// assert_eq!(
+    ///     config.to_string(&Params { name: String::from("ハナコ") }).unwrap(),
+    ///     "name=%83n%83i%83R"
+    /// );
+    /// ```
+    #[cfg(feature = "charset")]
+    pub fn charset(mut self, charset: &'static encoding_rs::Encoding) -> Self {
+        self.charset = Some(charset);
+        self
+    }
+
+    /// Enable or disable canonical serialization, so that equal values are always written as the same string,
+    /// such as for cache keys and request signatures.
+    ///
+    /// In canonical mode, the pairs are sorted by the segments of their keys rather than written in declaration order,
+    /// keeping the pairs of equal keys in order, and a key without value is written with an empty value.
+    /// With the percent-encoding of the configured [`Encoding`], which writes uppercase hexadecimal digits
+    /// and always writes a space the same way, the output is the same as [`canonicalize`](SerializerConfig::canonicalize)
+    /// gives for any query-string with the same pairs.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_structuredqs::SerializerConfig;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     sort: String,
+    ///     page: Page,
+    ///     q: String,
+    /// }
+    ///
+    /// #[derive(Serialize)]
+    /// struct Page {
+    ///     size: u32,
+    ///     number: u32,
+    /// }
+    ///
+    /// let params = Params {
+    ///     sort: String::from("date"),
+    ///     page: Page { size: 20, number: 2 },
+    ///     q: String::from("café au lait"),
+    /// };
+    /// let config = SerializerConfig::new().canonical(true);
+    /// let canonical = config.to_string(&params).unwrap();
+    /// assert_eq!(canonical, "page.number=2&page.size=20&q=caf%C3%A9+au+lait&sort=date");
+    /// assert_eq!(
+    ///     config.canonicalize("sort=date&q=caf%c3%a9%20au+lait&page.size=20&page.number=2").unwrap(),
+    ///     canonical
+    /// );
+    /// ```
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    /// Rewrite a query-string into the canonical form of [`canonical`](SerializerConfig::canonical) mode
+    /// with this configuration, whether or not the mode is enabled.
+    ///
+    /// The input is parsed as with the default [`DeserializerConfig`], except that keys and values are decoded
+    /// in the charset of this configuration and keys may have any number of segments, so that the output of
+    /// [`to_string`](SerializerConfig::to_string) in canonical mode is returned unchanged:
+    /// keys and values are percent-decoded, a `+` is decoded as a space, and empty pairs and key segments are dropped.
+    /// The pairs are then sorted and encoded again, with a `.` inside a key segment, such as one decoded from `%2E`,
+    /// encoded as `%2E`. Values are kept as they are, including their sequence separators.
+    ///
+    /// Use [`canonicalize_with`](SerializerConfig::canonicalize_with) to parse the input with another configuration,
+    /// such as one that keeps a `+` as it is or that limits untrusted input.
+    pub fn canonicalize(&self, input: &str) -> Result<String> {
+        let de_config = DeserializerConfig::default().max_depth(usize::MAX);
+        #[cfg(feature = "charset")]
+        let de_config = match self.charset {
+            Some(charset) => de_config.charset(crate::de::charset::Charset::Encoding(charset)),
+            None => de_config,
+        };
+        self.canonicalize_with(&de_config, input)
+    }
+
+    /// Rewrite a query-string into the canonical form of [`canonical`](SerializerConfig::canonical) mode
+    /// with this configuration, parsing it with `de_config`, including its limits. See [`canonicalize`](SerializerConfig::canonicalize).
+    ///
+    /// ```
+    /// use serde_structuredqs::{DeserializerConfig, SerializerConfig};
+    ///
+    /// let de_config = DeserializerConfig::new().plus_as_space(false);
+    /// assert_eq!(
+    ///     SerializerConfig::new().canonicalize_with(&de_config, "b=1+1&a=x%20y").unwrap(),
+    ///     "a=x+y&b=1%2B1"
+    /// );
+    /// ```
+    pub fn canonicalize_with(&self, de_config: &DeserializerConfig, input: &str) -> Result<String> {
+        let mut pairs = Parser::new(input.as_bytes(), *de_config)
+            .parse()?
+            .to_decoded_pairs()?;
+        pairs.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut output = String::new();
+        let mut encoder = Encoder::new(&mut output, *self);
+        for (segments, value) in &pairs {
+            encoder.append_segments(segments.iter().map(String::as_str), value);
+        }
+        Ok(output)
+    }
+
+    /// Serialize struct into `x-www-form-urlencoded` format string with this configuration.
+    pub fn to_string<T>(&self, value: &T) -> Result<String>
+    where
+        T: Serialize,
+    {
+        let mut output = String::new();
+        self.serialize_into(value, &mut output, None)?;
+        Ok(output)
+    }
+
+    /// Serialize struct into `x-www-form-urlencoded` format string with this configuration,
+    /// omitting every field whose pairs are all written exactly as the pairs of the same field in `defaults`.
+    ///
+    /// A field is omitted or written as a whole: if any of the pairs of a nested struct differs,
+    /// all of its pairs are written, so that the nested struct does not need defaults of its own.
+    /// A part of `defaults` that `value` does not write at all, such as a field that is `None` only in `value`,
+    /// at the top level or inside a written nested struct, is written as a single key with an empty value,
+    /// so that it is deserialized as `None` rather than as its default.
+    /// With the defaults as `#[serde(default)]`, the query-string is deserialized back into `value`.
+    ///
+    /// ```
+    /// use serde::{Deserialize, Serialize};
+    /// use serde_structuredqs::SerializerConfig;
+    ///
+    /// #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+    /// #[serde(default)]
+    /// struct Search {
+    ///     q: Option<String>,
+    ///     limit: u32,
+    ///     sort: String,
+    /// }
+    ///
+    /// impl Default for Search {
+    ///     fn default() -> Self {
+    ///         Search { q: None, limit: 20, sort: String::from("relevance") }
+    ///     }
+    /// }
+    ///
+    /// let search = Search { q: Some(String::from("rust")), ..Default::default() };
+    /// let query = SerializerConfig::new().to_string_diff(&search, &Search::default()).unwrap();
+    /// assert_eq!(query, "q=rust");
+    /// assert_eq!(serde_structuredqs::from_str::<Search>(&query).unwrap(), search);
+    /// ```
+    pub fn to_string_diff<T>(&self, value: &T, defaults: &T) -> Result<String>
+    where
+        T: Serialize,
+    {
+        let mut rendered = Defaults::default();
+        ser::serialize_at(&mut rendered, "", defaults, *self)?;
+
+        let mut output = String::new();
+        if self.canonical {
+            let mut pairs = Vec::new();
+            let mut diff = Diff::new(&mut pairs, rendered);
+            ser::serialize_at(&mut diff, "", value, *self)?;
+            diff.finish();
+            write_canonical(pairs, &mut Encoder::new(&mut output, *self));
+            return Ok(output);
+        }
+
+        let mut encoder = Encoder::new(&mut output, *self);
+        let mut diff = Diff::new(&mut encoder, rendered);
+        ser::serialize_at(&mut diff, "", value, *self)?;
+        diff.finish();
+        Ok(output)
+    }
+
+    /// Serialize struct into `x-www-form-urlencoded` format bytes with this configuration.
+    pub fn to_vec<T>(&self, value: &T) -> Result<Vec<u8>>
+    where
+        T: Serialize,
+    {
+        let mut output = Vec::new();
+        self.append_to_vec(value, &mut output)?;
+        Ok(output)
+    }
+
+    /// Serialize struct into `x-www-form-urlencoded` format and write it into `writer` with this configuration.
+    ///
+    /// Each pair is written with its own `write_all` as soon as it is serialized, without building the whole query-string first,
+    /// so an unbuffered writer such as a `TcpStream` is better wrapped in an `io::BufWriter`.
+    /// If serialization fails, the pairs before the failing one have already been written.
+    pub fn to_writer<T, W>(&self, value: &T, writer: W) -> Result<()>
+    where
+        T: Serialize,
+        W: io::Write,
+    {
+        let mut writer = Writer::new(writer, *self);
+        self.serialize_to(value, &mut writer)?;
+        Ok(writer.finish()?)
+    }
+
+    /// Append the pairs of struct to `output`, a buffer which may already have pairs, with this configuration.
+    ///
+    /// A `&` is written before the pairs unless `output` is empty or already ends with `&`.
+    /// The pairs are written straight into `output`, so a buffer allocated with enough capacity is not reallocated.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_structuredqs::SerializerConfig;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Page {
+    ///     page: u32,
+    /// }
+    ///
+    /// let mut body = Vec::with_capacity(64);
+    /// body.extend_from_slice(b"q=rust");
+    /// SerializerConfig::new().append_to_vec(&Page { page: 2 }, &mut body).unwrap();
+    /// assert_eq!(body, b"q=rust&page=2");
+    /// assert_eq!(body.capacity(), 64);
+    /// ```
+    pub fn append_to_vec<T>(&self, value: &T, output: &mut Vec<u8>) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let leading = (!output.is_empty() && !output.ends_with(b"&")).then_some('&');
+        let mut writer = Writer::new(output, *self).leading(leading);
+        self.serialize_to(value, &mut writer)?;
+        Ok(writer.finish()?)
+    }
+
+    /// Append the pairs of struct to `output`, a query-string which may already have pairs,
+    /// with this configuration.
+    ///
+    /// A `&` is written before the pairs unless `output` is empty or already ends with `&`.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_structuredqs::SerializerConfig;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Page {
+    ///     page: u32,
+    /// }
+    ///
+    /// let mut body = String::from("q=rust");
+    /// SerializerConfig::new().append_to_string(&Page { page: 2 }, &mut body).unwrap();
+    /// assert_eq!(body, "q=rust&page=2");
+    /// ```
+    pub fn append_to_string<T>(&self, value: &T, output: &mut String) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let leading = (!output.is_empty() && !output.ends_with('&')).then_some('&');
+        self.serialize_into(value, output, leading)
+    }
+
+    /// Append the pairs of struct to the query of `url` with this configuration.
+    ///
+    /// A `?` is written before the pairs if `url` has no query yet, and a `&` if its query already has pairs.
+    /// The fragment of `url`, if any, is kept after the query.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_structuredqs::SerializerConfig;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Page {
+    ///     page: u32,
+    /// }
+    ///
+    /// let config = SerializerConfig::new();
+    /// let mut url = String::from("https://example.com/search#results");
+    /// config.append_to_url(&Page { page: 2 }, &mut url).unwrap();
+    /// assert_eq!(url, "https://example.com/search?page=2#results");
+    ///
+    /// let mut url = String::from("https://example.com/search?q=rust");
+    /// config.append_to_url(&Page { page: 2 }, &mut url).unwrap();
+    /// assert_eq!(url, "https://example.com/search?q=rust&page=2");
+    /// ```
+    pub fn append_to_url<T>(&self, value: &T, url: &mut String) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let fragment = url.find('#').map(|position| url.split_off(position));
+        let leading = match url.find('?') {
+            None => Some('?'),
+            Some(_) if url.ends_with('?') || url.ends_with('&') => None,
+            Some(_) => Some('&'),
+        };
+        let result = self.serialize_into(value, url, leading);
+        if let Some(fragment) = fragment {
+            url.push_str(&fragment);
+        }
+        result
+    }
+
+    /// Serialize struct into `(key, value)` pairs with this configuration, without percent-encoding them.
+    ///
+    /// Keys are joined and sequences are separated exactly as by [`to_string`](SerializerConfig::to_string),
+    /// and a key without value, such as a unit, is paired with an empty value.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_structuredqs::SerializerConfig;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     q: String,
+    ///     page: Page,
+    ///     tags: Vec<String>,
+    /// }
+    ///
+    /// #[derive(Serialize)]
+    /// struct Page {
+    ///     size: u32,
+    /// }
+    ///
+    /// let params = Params {
+    ///     q: String::from("a&b"),
+    ///     page: Page { size: 20 },
+    ///     tags: vec![String::from("x"), String::from("y,z")],
+    /// };
+    /// assert_eq!(
+    ///     SerializerConfig::new().to_pairs(&params).unwrap(),
+    ///     vec![
+    ///         (String::from("q"), String::from("a&b")),
+    ///         (String::from("page.size"), String::from("20")),
+    ///         (String::from("tags"), String::from("x,y\\,z")),
+    ///     ]
+    /// );
+    /// ```
+    pub fn to_pairs<T>(&self, value: &T) -> Result<Vec<(String, String)>>
+    where
+        T: Serialize,
+    {
+        let mut pairs = Vec::new();
+        let mut buffers = Buffers::default();
+        value.serialize(TopLevelSerializer::new(&mut pairs, &mut buffers, *self))?;
+        if self.canonical {
+            sort_canonically(&mut pairs);
+        }
+        Ok(pairs)
+    }
+
+    /// Append the pairs of struct to `serializer`, after the pairs it already has, with this configuration.
+    ///
+    /// Only the separators of this configuration are used: keys and values are encoded by `serializer`,
+    /// with its own encoding override if it has one.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_structuredqs::{form_urlencoded, Separators, SerializerConfig};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Filter {
+    ///     ids: Vec<u32>,
+    /// }
+    ///
+    /// let config = SerializerConfig::new().separators(Separators::new(&['|']).unwrap());
+    /// let mut serializer = form_urlencoded::Serializer::new(String::new());
+    /// serializer.append_pair("q", "rust");
+    /// config.to_serializer(&Filter { ids: vec![1, 2] }, &mut serializer).unwrap();
+    /// assert_eq!(serializer.finish(), "q=rust&ids=1%7C2");
+    /// ```
+    pub fn to_serializer<T, U>(
+        &self,
+        value: &T,
+        serializer: &mut form_urlencoded::Serializer<'_, U>,
+    ) -> Result<()>
+    where
+        T: Serialize,
+        U: Target,
+    {
+        self.serialize_to(value, serializer)
+    }
+
+    /// Append the pairs of struct to `output`, writing `leading` before them if there is any.
+    fn serialize_into<T>(&self, value: &T, output: &mut String, leading: Option<char>) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.serialize_to(value, &mut Encoder::new(output, *self).leading(leading))
+    }
+
+    /// Append the pairs of struct to `sink`, sorted in canonical mode.
+    fn serialize_to<T, S>(&self, value: &T, sink: &mut S) -> Result<()>
+    where
+        T: Serialize,
+        S: Sink,
+    {
+        if self.canonical {
+            write_canonical(self.to_pairs(value)?, sink);
+            return Ok(());
+        }
+        let mut buffers = Buffers::default();
+        value.serialize(TopLevelSerializer::new(sink, &mut buffers, *self))?;
+        Ok(())
+    }
+}
+
+/// Sort the pairs and append them to `sink`.
+fn write_canonical<S: Sink>(mut pairs: Vec<(String, String)>, sink: &mut S) {
+    sort_canonically(&mut pairs);
+    for (key, value) in &pairs {
+        sink.append_pair(key, value);
+    }
+}
+
+/// Sort the pairs by the segments of their keys, as the deserializer does, keeping equal keys in order.
+fn sort_canonically(pairs: &mut [(String, String)]) {
+    pairs.sort_by(|(a, _), (b, _)| a.split('.').cmp(b.split('.')));
+}
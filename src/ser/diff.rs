@@ -0,0 +1,150 @@
+use crate::ser::encoder::Sink;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Bound,
+};
+
+/// Records the pairs of the default value, along with whether each one has a value.
+#[derive(Default)]
+pub(crate) struct Defaults(pub BTreeMap<String, Option<String>>);
+
+impl Sink for Defaults {
+    fn append_pair(&mut self, key: &str, value: &str) -> &mut Self {
+        self.0.insert(key.to_owned(), Some(value.to_owned()));
+        self
+    }
+
+    fn append_key_only(&mut self, key: &str) -> &mut Self {
+        self.0.insert(key.to_owned(), None);
+        self
+    }
+}
+
+/// Writes into `sink` the top-level fields whose pairs differ from the defaults.
+///
+/// A field is written as a whole, so that a nested struct is deserialized from its own pairs
+/// rather than partly from the defaults.
+pub(crate) struct Diff<'s, S> {
+    sink: &'s mut S,
+    defaults: BTreeMap<String, Option<String>>,
+    /// The pairs of the top-level field being serialized.
+    field: Vec<(String, Option<String>)>,
+    /// Whether a pair of the field differs from the defaults.
+    changed: bool,
+}
+
+impl<'s, S> Diff<'s, S>
+where
+    S: Sink,
+{
+    pub fn new(sink: &'s mut S, defaults: Defaults) -> Self {
+        Self {
+            sink,
+            defaults: defaults.0,
+            field: Vec::new(),
+            changed: false,
+        }
+    }
+
+    /// Write the last field, then the fields of the defaults that the value did not write at all,
+    /// each as a single key with an empty value, so that they are not deserialized as their default.
+    pub fn finish(mut self) {
+        self.flush();
+        let names: BTreeSet<&str> = self.defaults.keys().map(|key| top_level(key)).collect();
+        for name in names {
+            self.sink.append_pair(name, "");
+        }
+    }
+
+    fn push(&mut self, key: &str, value: Option<&str>) {
+        if self
+            .field
+            .first()
+            .is_some_and(|(first, _)| top_level(first) != top_level(key))
+        {
+            self.flush();
+        }
+        let value = value.map(ToOwned::to_owned);
+        if self.defaults.remove(key).as_ref() != Some(&value) {
+            self.changed = true;
+        }
+        self.field.push((key.to_owned(), value));
+    }
+
+    /// Write the pairs of the current field if any of them, or a pair of the defaults it lacks, differs.
+    ///
+    /// Each lacking part is written as the shortest key under which the field has no pair, with an empty value,
+    /// so that it is deserialized as `None` rather than as its default.
+    fn flush(&mut self) {
+        let Some((first, _)) = self.field.first() else {
+            return;
+        };
+        let name = top_level(first);
+        let prefix = format!("{}.", name);
+        let missing: Vec<String> = self
+            .defaults
+            .range::<str, _>((Bound::Included(name), Bound::Unbounded))
+            .map(|(key, _)| key)
+            .take_while(|key| key.starts_with(name))
+            .filter(|key| key.as_str() == name || key.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for key in &missing {
+            self.defaults.remove(key);
+        }
+
+        if self.changed || !missing.is_empty() {
+            for (key, value) in &self.field {
+                match value {
+                    Some(value) => self.sink.append_pair(key, value),
+                    None => self.sink.append_key_only(key),
+                };
+            }
+            let mut written = BTreeSet::new();
+            for key in &missing {
+                let root = self.unwritten_root(key);
+                if written.insert(root) {
+                    self.sink.append_pair(root, "");
+                }
+            }
+        }
+        self.field.clear();
+        self.changed = false;
+    }
+}
+
+impl<'s, S> Diff<'s, S> {
+    /// The shortest prefix of `key` under which the current field has no pair.
+    fn unwritten_root<'k>(&self, key: &'k str) -> &'k str {
+        key.match_indices('.')
+            .map(|(end, _)| &key[..end])
+            .chain(std::iter::once(key))
+            .find(|prefix| {
+                !self.field.iter().any(|(written, _)| {
+                    written.starts_with(prefix)
+                        && matches!(written.as_bytes().get(prefix.len()), None | Some(b'.'))
+                })
+            })
+            .unwrap_or(key)
+    }
+}
+
+/// The first segment of a key.
+fn top_level(key: &str) -> &str {
+    key.split('.').next().unwrap_or(key)
+}
+
+impl<'s, S> Sink for Diff<'s, S>
+where
+    S: Sink,
+{
+    fn append_pair(&mut self, key: &str, value: &str) -> &mut Self {
+        self.push(key, Some(value));
+        self
+    }
+
+    fn append_key_only(&mut self, key: &str) -> &mut Self {
+        self.push(key, None);
+        self
+    }
+}
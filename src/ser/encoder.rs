@@ -0,0 +1,258 @@
+use crate::ser::config::SerializerConfig;
+
+use form_urlencoded::Target;
+use percent_encoding::{AsciiSet, CONTROLS, NON_ALPHANUMERIC};
+use std::io;
+
+/// Characters left as they are by [`Encoding::Rfc3986`]: the unreserved characters of RFC 3986.
+const RFC3986: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Characters left as they are by [`Encoding::Readable`].
+const READABLE: &AsciiSet = &RFC3986.remove(b',').remove(b':').remove(b'/');
+
+/// How keys and values are percent-encoded when serializing.
+///
+/// Every encoding but [`Encoding::None`] is decoded by the deserializer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// `application/x-www-form-urlencoded`: everything but `*` `-` `.` `_` and alphanumerics is encoded,
+    /// and spaces become `+`.
+    #[default]
+    FormUrlencoded,
+    /// RFC 3986 query component: everything but `-` `.` `_` `~` and alphanumerics is encoded,
+    /// and spaces become `%20`.
+    Rfc3986,
+    /// Like [`Encoding::Rfc3986`], but `,` `:` and `/` are also left as they are,
+    /// for URLs meant to be read by people.
+    Readable,
+    /// Nothing is encoded. The output is not guaranteed to be parsed back and is meant for debugging.
+    None,
+}
+
+impl Encoding {
+    fn encode_into(self, output: &mut String, input: &[u8]) {
+        match self {
+            Encoding::FormUrlencoded => output.extend(form_urlencoded::byte_serialize(input)),
+            Encoding::Rfc3986 => output.extend(percent_encoding::percent_encode(input, RFC3986)),
+            Encoding::Readable => output.extend(percent_encoding::percent_encode(input, READABLE)),
+            Encoding::None => match std::str::from_utf8(input) {
+                Ok(input) => output.push_str(input),
+                // Bytes of other charsets cannot be written as they are.
+                Err(_) => output.extend(percent_encoding::percent_encode(input, CONTROLS)),
+            },
+        }
+    }
+}
+
+/// Destination of the serialized `key=value` pairs.
+pub trait Sink {
+    fn append_pair(&mut self, key: &str, value: &str) -> &mut Self;
+
+    fn append_key_only(&mut self, key: &str) -> &mut Self;
+}
+
+/// `Encoder` writes `key=value` pairs into a `Target`, percent-encoded with an `Encoding`.
+pub struct Encoder<T>
+where
+    T: Target,
+{
+    target: T,
+    start_position: usize,
+    /// Written before the first pair, when appending to a buffer that already has pairs or a URL.
+    leading: Option<char>,
+    encoding: Encoding,
+    #[cfg(feature = "charset")]
+    charset: Option<&'static encoding_rs::Encoding>,
+}
+
+impl<T> Encoder<T>
+where
+    T: Target,
+{
+    pub fn new(mut target: T, config: SerializerConfig) -> Self {
+        let start_position = target.as_mut_string().len();
+        Self {
+            target,
+            start_position,
+            leading: None,
+            encoding: config.encoding,
+            #[cfg(feature = "charset")]
+            charset: config.charset,
+        }
+    }
+
+    /// Write `leading` before the first pair, if there is any.
+    pub fn leading(mut self, leading: Option<char>) -> Self {
+        self.leading = leading;
+        self
+    }
+
+    /// Encode the string in the configured charset, then percent-encode it.
+    fn encode_into(&mut self, input: &str) {
+        #[cfg(feature = "charset")]
+        if let Some(charset) = self.charset {
+            let (bytes, _, _) = charset.encode(input);
+            return self
+                .encoding
+                .encode_into(self.target.as_mut_string(), &bytes);
+        }
+        self.encoding
+            .encode_into(self.target.as_mut_string(), input.as_bytes());
+    }
+
+    /// Append a pair whose key is given by its segments.
+    ///
+    /// A `.` inside a segment is percent-encoded, so that it is not read as a separator of segments.
+    pub fn append_segments<'k>(
+        &mut self,
+        segments: impl IntoIterator<Item = &'k str>,
+        value: &str,
+    ) -> &mut Self {
+        self.append_separator_if_needed();
+        for (i, segment) in segments.into_iter().enumerate() {
+            if i > 0 {
+                self.target.as_mut_string().push('.');
+            }
+            let start = self.target.as_mut_string().len();
+            self.encode_into(segment);
+            let output = self.target.as_mut_string();
+            if output[start..].contains('.') {
+                let escaped = output[start..].replace('.', "%2E");
+                output.truncate(start);
+                output.push_str(&escaped);
+            }
+        }
+        self.target.as_mut_string().push('=');
+        self.encode_into(value);
+        self
+    }
+
+    fn append_separator_if_needed(&mut self) {
+        let output = self.target.as_mut_string();
+        if output.len() > self.start_position {
+            output.push('&');
+        } else if let Some(leading) = self.leading {
+            output.push(leading);
+        }
+    }
+}
+
+impl<T> Sink for Encoder<T>
+where
+    T: Target,
+{
+    fn append_pair(&mut self, key: &str, value: &str) -> &mut Self {
+        self.append_separator_if_needed();
+        self.encode_into(key);
+        self.target.as_mut_string().push('=');
+        self.encode_into(value);
+        self
+    }
+
+    fn append_key_only(&mut self, key: &str) -> &mut Self {
+        self.append_separator_if_needed();
+        self.encode_into(key);
+        self
+    }
+}
+
+/// `Writer` encodes each pair into a reused buffer and writes it into an `io::Write` as soon as it is serialized.
+///
+/// The first error stops the writing and is returned by [`Writer::finish`].
+pub struct Writer<W>
+where
+    W: io::Write,
+{
+    writer: W,
+    buffer: String,
+    /// Written before the first pair, and replaced by `&` once a pair is written.
+    leading: Option<char>,
+    config: SerializerConfig,
+    result: io::Result<()>,
+}
+
+impl<W> Writer<W>
+where
+    W: io::Write,
+{
+    pub fn new(writer: W, config: SerializerConfig) -> Self {
+        Self {
+            writer,
+            buffer: String::new(),
+            leading: None,
+            config,
+            result: Ok(()),
+        }
+    }
+
+    /// Write `leading` before the first pair, if there is any.
+    pub fn leading(mut self, leading: Option<char>) -> Self {
+        self.leading = leading;
+        self
+    }
+
+    /// Returns the first error of the writer, if any.
+    pub fn finish(self) -> io::Result<()> {
+        self.result
+    }
+
+    fn write(&mut self, append: impl FnOnce(&mut Encoder<&mut String>)) {
+        if self.result.is_err() {
+            return;
+        }
+        self.buffer.clear();
+        append(&mut Encoder::new(&mut self.buffer, self.config).leading(self.leading));
+        self.leading = Some('&');
+        self.result = self.writer.write_all(self.buffer.as_bytes());
+    }
+}
+
+impl<W> Sink for Writer<W>
+where
+    W: io::Write,
+{
+    fn append_pair(&mut self, key: &str, value: &str) -> &mut Self {
+        self.write(|encoder| {
+            encoder.append_pair(key, value);
+        });
+        self
+    }
+
+    fn append_key_only(&mut self, key: &str) -> &mut Self {
+        self.write(|encoder| {
+            encoder.append_key_only(key);
+        });
+        self
+    }
+}
+
+/// A `form_urlencoded::Serializer` writes the pairs with its own encoding.
+impl<'a, T> Sink for form_urlencoded::Serializer<'a, T>
+where
+    T: Target,
+{
+    fn append_pair(&mut self, key: &str, value: &str) -> &mut Self {
+        form_urlencoded::Serializer::append_pair(self, key, value)
+    }
+
+    fn append_key_only(&mut self, key: &str) -> &mut Self {
+        form_urlencoded::Serializer::append_key_only(self, key)
+    }
+}
+
+/// A `Vec` collects the pairs without encoding them. A key without value is paired with an empty value.
+impl Sink for Vec<(String, String)> {
+    fn append_pair(&mut self, key: &str, value: &str) -> &mut Self {
+        self.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    fn append_key_only(&mut self, key: &str) -> &mut Self {
+        self.push((key.to_owned(), String::new()));
+        self
+    }
+}
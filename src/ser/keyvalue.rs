@@ -1,24 +1,22 @@
-use crate::error::{Error, Result};
-use form_urlencoded::Target;
+use crate::{
+    error::{Error, Result},
+    ser::encoder::Sink,
+};
 use serde::{de::Error as _, ser, Serialize};
-use std::borrow::Cow;
 
-pub struct KeyValueSerializer<'input, 'output, T>
+pub struct KeyValueSerializer<'output, S>
 where
-    T: Target,
+    S: Sink,
 {
-    encoder: &'output mut form_urlencoded::Serializer<'input, T>,
-    key: Cow<'static, str>,
+    encoder: &'output mut S,
+    key: &'output str,
 }
 
-impl<'input, 'output, T> KeyValueSerializer<'input, 'output, T>
+impl<'output, S> KeyValueSerializer<'output, S>
 where
-    T: 'output + Target,
+    S: 'output + Sink,
 {
-    pub fn new(
-        encoder: &'output mut form_urlencoded::Serializer<'input, T>,
-        key: Cow<'static, str>,
-    ) -> Self {
+    pub fn new(encoder: &'output mut S, key: &'output str) -> Self {
         Self { encoder, key }
     }
 }
@@ -39,40 +37,34 @@ macro_rules! serialize_float {
     };
 }
 
-impl<'input, 'output, T> KeyValueSerializer<'input, 'output, T>
+impl<'output, S> KeyValueSerializer<'output, S>
 where
-    T: 'output + Target,
+    S: 'output + Sink,
 {
-    fn serialize_integer<I>(
-        self,
-        value: I,
-    ) -> Result<&'output mut form_urlencoded::Serializer<'input, T>>
+    fn serialize_integer<I>(self, value: I) -> Result<&'output mut S>
     where
         I: itoa::Integer,
     {
         let mut buf = itoa::Buffer::new();
         let value = buf.format(value);
-        Ok(self.encoder.append_pair(&self.key, value))
+        Ok(self.encoder.append_pair(self.key, value))
     }
 
-    fn serialize_float<F>(
-        self,
-        value: F,
-    ) -> Result<&'output mut form_urlencoded::Serializer<'input, T>>
+    fn serialize_float<F>(self, value: F) -> Result<&'output mut S>
     where
         F: ryu::Float,
     {
         let mut buf = ryu::Buffer::new();
         let value = buf.format(value);
-        Ok(self.encoder.append_pair(&self.key, value))
+        Ok(self.encoder.append_pair(self.key, value))
     }
 }
 
-impl<'input, 'output, T> ser::Serializer for KeyValueSerializer<'input, 'output, T>
+impl<'output, S> ser::Serializer for KeyValueSerializer<'output, S>
 where
-    T: 'output + Target,
+    S: 'output + Sink,
 {
-    type Ok = &'output mut form_urlencoded::Serializer<'input, T>;
+    type Ok = &'output mut S;
     type Error = Error;
     type SerializeSeq = ser::Impossible<Self::Ok, Error>;
     type SerializeTuple = ser::Impossible<Self::Ok, Error>;
@@ -96,26 +88,26 @@ where
     fn serialize_bool(self, value: bool) -> Result<Self::Ok> {
         Ok(self
             .encoder
-            .append_pair(&self.key, if value { "true" } else { "false" }))
+            .append_pair(self.key, if value { "true" } else { "false" }))
     }
     fn serialize_char(self, value: char) -> Result<Self::Ok> {
-        Ok(self.encoder.append_pair(&self.key, &value.to_string()))
+        Ok(self.encoder.append_pair(self.key, &value.to_string()))
     }
 
     fn serialize_str(self, value: &str) -> Result<Self::Ok> {
-        Ok(self.encoder.append_pair(&self.key, value))
+        Ok(self.encoder.append_pair(self.key, value))
     }
 
     fn serialize_unit(self) -> Result<Self::Ok> {
-        Ok(self.encoder.append_key_only(&self.key))
+        Ok(self.encoder.append_key_only(self.key))
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
-        Ok(self.encoder.append_key_only(&self.key))
+        Ok(self.encoder.append_key_only(self.key))
     }
 
     fn serialize_none(self) -> Result<Self::Ok> {
-        Ok(self.encoder.append_key_only(&self.key))
+        Ok(self.encoder.append_key_only(self.key))
     }
 
     fn serialize_some<U>(self, value: &U) -> Result<Self::Ok>
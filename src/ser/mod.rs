@@ -1,9 +1,21 @@
+pub mod builder;
+pub mod config;
+mod diff;
+pub mod encoder;
 mod keyvalue;
 mod seq;
 mod toplevel;
 
-use crate::{error::Result, ser::toplevel::TopLevelSerializer};
+use crate::{
+    error::Result,
+    ser::{
+        config::SerializerConfig,
+        encoder::Sink,
+        toplevel::{Buffers, TopLevelSerializer},
+    },
+};
 use serde::Serialize;
+use std::io;
 
 /// Serialize struct into `x-www-form-urlencoded` format string.
 /// For fields that have a structure as their value, the field name is concatenated with the key of the structure, resulting in `{parentkey}.{childkey}={value}`.
@@ -36,7 +48,126 @@ pub fn to_string<T>(value: &T) -> Result<String>
 where
     T: Serialize,
 {
-    let mut encoder = form_urlencoded::Serializer::new(String::from(""));
-    value.serialize(TopLevelSerializer::new(&mut encoder))?;
-    Ok(encoder.finish())
+    SerializerConfig::default().to_string(value)
+}
+
+/// Serialize struct into `x-www-form-urlencoded` format bytes.
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize,
+{
+    SerializerConfig::default().to_vec(value)
+}
+
+/// Serialize struct into `x-www-form-urlencoded` format and write it into `writer`, one pair at a time.
+/// See [`SerializerConfig::to_writer`].
+/// ```
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Params {
+///     q: String,
+/// }
+///
+/// let mut body = Vec::new();
+/// serde_structuredqs::to_writer(&Params { q: String::from("a b") }, &mut body).unwrap();
+/// assert_eq!(body, b"q=a+b");
+/// ```
+pub fn to_writer<T, W>(value: &T, writer: W) -> Result<()>
+where
+    T: Serialize,
+    W: io::Write,
+{
+    SerializerConfig::default().to_writer(value, writer)
+}
+
+/// Append the pairs of struct to `output`, a query-string which may already have pairs.
+/// See [`SerializerConfig::append_to_string`].
+pub fn append_to_string<T>(value: &T, output: &mut String) -> Result<()>
+where
+    T: Serialize,
+{
+    SerializerConfig::default().append_to_string(value, output)
+}
+
+/// Append the pairs of struct to `output`, a buffer which may already have pairs.
+/// See [`SerializerConfig::append_to_vec`].
+pub fn append_to_vec<T>(value: &T, output: &mut Vec<u8>) -> Result<()>
+where
+    T: Serialize,
+{
+    SerializerConfig::default().append_to_vec(value, output)
+}
+
+/// Append the pairs of struct to the query of `url`. See [`SerializerConfig::append_to_url`].
+pub fn append_to_url<T>(value: &T, url: &mut String) -> Result<()>
+where
+    T: Serialize,
+{
+    SerializerConfig::default().append_to_url(value, url)
+}
+
+/// Append the pairs of struct to `serializer`, after the pairs it already has,
+/// such as the `query_pairs_mut()` of a `url::Url`. See [`SerializerConfig::to_serializer`].
+pub fn to_serializer<T, U>(
+    value: &T,
+    serializer: &mut form_urlencoded::Serializer<'_, U>,
+) -> Result<()>
+where
+    T: Serialize,
+    U: form_urlencoded::Target,
+{
+    SerializerConfig::default().to_serializer(value, serializer)
+}
+
+/// Serialize struct into `(key, value)` pairs without percent-encoding them. See [`SerializerConfig::to_pairs`].
+pub fn to_pairs<T>(value: &T) -> Result<Vec<(String, String)>>
+where
+    T: Serialize,
+{
+    SerializerConfig::default().to_pairs(value)
+}
+
+/// Serialize a value into `sink` under `prefix`: a struct as nested keys,
+/// or any other value as the value of `prefix` itself. An empty prefix serializes a struct at the top level.
+pub(crate) fn serialize_at<S, T>(
+    sink: &mut S,
+    prefix: &str,
+    value: &T,
+    config: SerializerConfig,
+) -> Result<()>
+where
+    S: Sink,
+    T: Serialize,
+{
+    let mut buffers = Buffers::default();
+    value.serialize(TopLevelSerializer::with_prefix(
+        sink,
+        &mut buffers,
+        config,
+        prefix,
+    ))?;
+    Ok(())
+}
+
+/// Serialize struct into `x-www-form-urlencoded` format string, omitting the pairs equal to those of `defaults`.
+/// See [`SerializerConfig::to_string_diff`].
+pub fn to_string_diff<T>(value: &T, defaults: &T) -> Result<String>
+where
+    T: Serialize,
+{
+    SerializerConfig::default().to_string_diff(value, defaults)
+}
+
+/// Rewrite a query-string into a canonical form, with its pairs sorted by key and encoded in a single way.
+/// See [`SerializerConfig::canonicalize`].
+///
+/// ```
+/// assert_eq!(
+///     serde_structuredqs::canonicalize("b=x%2cy&a.d=%7e&a.c=1+2").unwrap(),
+///     "a.c=1+2&a.d=%7E&b=x%2Cy"
+/// );
+/// ```
+pub fn canonicalize(input: &str) -> Result<String> {
+    SerializerConfig::default().canonicalize(input)
 }
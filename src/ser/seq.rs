@@ -1,53 +1,263 @@
-use crate::error::{Error, Result};
-use form_urlencoded::Target;
+use crate::{
+    error::{Error, Result},
+    sequence::{self, Separators},
+    ser::encoder::Sink,
+};
 use serde::{de::Error as _, ser, Serialize};
-use std::borrow::Cow;
 
-pub struct SeqSerializer<'input, 'output, T>
+/// `SeqSerializer` takes a sequence or a tuple and serializes it as a single value.
+pub struct SeqSerializer<'output, S>
 where
-    T: Target,
+    S: Sink,
 {
-    encoder: &'output mut form_urlencoded::Serializer<'input, T>,
-    key: Cow<'static, str>,
-    container: Vec<Cow<'input, str>>,
+    encoder: &'output mut S,
+    key: &'output str,
+    elements: Elements<'output>,
 }
 
-impl<'input, 'output, T> SeqSerializer<'input, 'output, T>
+impl<'output, S> SeqSerializer<'output, S>
 where
-    T: 'output + Target,
+    S: 'output + Sink,
 {
     pub fn new(
-        encoder: &'output mut form_urlencoded::Serializer<'input, T>,
-        key: Cow<'static, str>,
-        len: Option<usize>,
+        encoder: &'output mut S,
+        key: &'output str,
+        separators: Separators,
+        output: &'output mut String,
     ) -> Self {
         Self {
             encoder,
             key,
-            container: Vec::with_capacity(len.unwrap_or(0)),
+            elements: Elements::new(separators, output),
         }
     }
+
+    fn serialize_element<U>(&mut self, value: &U) -> Result<()>
+    where
+        U: Serialize + ?Sized,
+    {
+        value.serialize(&mut self.elements)
+    }
+
+    fn end(mut self) -> Result<&'output mut S> {
+        self.elements.finish()?;
+        let encoder = self.encoder.append_pair(self.key, self.elements.value());
+        self.elements.clear();
+        Ok(encoder)
+    }
+}
+
+impl<'output, S> ser::SerializeSeq for SeqSerializer<'output, S>
+where
+    S: 'output + Sink,
+{
+    type Ok = &'output mut S;
+    type Error = Error;
+
+    fn serialize_element<U>(&mut self, value: &U) -> Result<()>
+    where
+        U: Serialize + ?Sized,
+    {
+        self.serialize_element(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.end()
+    }
+}
+
+impl<'output, S> ser::SerializeTuple for SeqSerializer<'output, S>
+where
+    S: 'output + Sink,
+{
+    type Ok = &'output mut S;
+    type Error = Error;
+
+    fn serialize_element<U>(&mut self, value: &U) -> Result<()>
+    where
+        U: Serialize + ?Sized,
+    {
+        self.serialize_element(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.end()
+    }
 }
 
-impl<'input, 'output, T> ser::SerializeSeq for SeqSerializer<'input, 'output, T>
+impl<'output, S> ser::SerializeTupleStruct for SeqSerializer<'output, S>
 where
-    T: Target,
+    S: 'output + Sink,
 {
-    type Ok = &'output mut form_urlencoded::Serializer<'input, T>;
+    type Ok = &'output mut S;
     type Error = Error;
 
-    fn serialize_element<S>(&mut self, value: &S) -> Result<()>
+    fn serialize_field<U>(&mut self, value: &U) -> Result<()>
     where
-        S: Serialize + ?Sized,
+        U: Serialize + ?Sized,
     {
-        value.serialize(self)?;
+        self.serialize_element(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.end()
+    }
+}
+
+/// The escaped elements of a sequence, written one by one at the end of `output`.
+///
+/// Elements are joined with the innermost separator as they are written, and the positions of
+/// the separators are recorded so that they can be replaced once nested sequences call for another one.
+pub struct Elements<'a> {
+    separators: Separators,
+    output: &'a mut String,
+    /// The position in `output` where the sequence starts.
+    start: usize,
+    count: usize,
+    /// The positions of the separators between the elements.
+    boundaries: Vec<usize>,
+    /// The highest separator level among the elements that are sequences themselves.
+    nested: Option<usize>,
+}
+
+impl<'a> Elements<'a> {
+    fn new(separators: Separators, output: &'a mut String) -> Self {
+        let start = output.len();
+        Self {
+            separators,
+            output,
+            start,
+            count: 0,
+            boundaries: Vec::new(),
+            nested: None,
+        }
+    }
+
+    /// Write the separator before the next element.
+    fn next_element(&mut self) {
+        if self.count > 0 {
+            self.boundaries.push(self.output.len());
+            self.output.push_str(self.separators.innermost());
+        }
+        self.count += 1;
+    }
+
+    fn push_str(&mut self, value: &str) {
+        self.next_element();
+        self.separators.escape_into(self.output, value);
+    }
+
+    /// Write an element that never needs escaping, such as a marker.
+    fn push_raw(&mut self, value: &str) {
+        self.next_element();
+        self.output.push_str(value);
+    }
+
+    /// Complete the value and return the level of its separator.
+    fn finish(&mut self) -> Result<usize> {
+        if self.count == 0 {
+            self.output.push_str(self.separators.empty_sequence());
+            return Ok(0);
+        }
+
+        let level = self.nested.map_or(0, |level| level + 1);
+        let separator = self
+            .separators
+            .get(level)
+            .ok_or_else(|| Error::custom("sequence is nested deeper than the separators allow"))?;
+
+        if level > 0 {
+            let mut buf = [0; 4];
+            let separator = separator.encode_utf8(&mut buf);
+            for &position in &self.boundaries {
+                self.output.replace_range(position..position + 1, separator);
+            }
+            // A single sequence element must still be told apart from the elements it contains.
+            if self.count == 1 {
+                self.output.push_str(separator);
+            }
+        }
+
+        Ok(level)
+    }
+
+    fn value(&self) -> &str {
+        &self.output[self.start..]
+    }
+
+    fn clear(&mut self) {
+        self.output.truncate(self.start);
+    }
+}
+
+/// `NestedSeqSerializer` serializes a sequence that is an element of another sequence.
+pub struct NestedSeqSerializer<'a> {
+    parent_nested: &'a mut Option<usize>,
+    elements: Elements<'a>,
+}
+
+impl<'a> NestedSeqSerializer<'a> {
+    fn new(parent: &'a mut Elements<'_>) -> Self {
+        parent.next_element();
+        let elements = Elements::new(parent.separators, parent.output);
+        Self {
+            parent_nested: &mut parent.nested,
+            elements,
+        }
+    }
+
+    fn end(mut self) -> Result<()> {
+        let level = self.elements.finish()?;
+        *self.parent_nested = (*self.parent_nested).max(Some(level));
         Ok(())
     }
+}
+
+impl<'a> ser::SerializeSeq for NestedSeqSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<U>(&mut self, value: &U) -> Result<()>
+    where
+        U: Serialize + ?Sized,
+    {
+        value.serialize(&mut self.elements)
+    }
 
     fn end(self) -> Result<Self::Ok> {
-        Ok(self
-            .encoder
-            .append_pair(&self.key, &self.container.join(",")))
+        self.end()
+    }
+}
+
+impl<'a> ser::SerializeTuple for NestedSeqSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<U>(&mut self, value: &U) -> Result<()>
+    where
+        U: Serialize + ?Sized,
+    {
+        value.serialize(&mut self.elements)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.end()
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for NestedSeqSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<U>(&mut self, value: &U) -> Result<()>
+    where
+        U: Serialize + ?Sized,
+    {
+        value.serialize(&mut self.elements)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.end()
     }
 }
 
@@ -55,8 +265,7 @@ macro_rules! serialize_integer {
     ($ty:ty, $method:ident) => {
         fn $method(self, value: $ty) -> Result<Self::Ok> {
             let mut buf = itoa::Buffer::new();
-            let value = buf.format(value);
-            self.container.push(Cow::Owned(value.to_owned()));
+            self.push_str(buf.format(value));
             Ok(())
         }
     };
@@ -66,22 +275,18 @@ macro_rules! serialize_float {
     ($ty:ty, $method:ident) => {
         fn $method(self, value: $ty) -> Result<Self::Ok> {
             let mut buf = ryu::Buffer::new();
-            let value = buf.format(value);
-            self.container.push(Cow::Owned(value.to_owned()));
+            self.push_str(buf.format(value));
             Ok(())
         }
     };
 }
 
-impl<'input, 'output, T> ser::Serializer for &mut SeqSerializer<'input, 'output, T>
-where
-    T: 'output + Target,
-{
+impl<'a, 'b> ser::Serializer for &'a mut Elements<'b> {
     type Ok = ();
     type Error = Error;
-    type SerializeSeq = ser::Impossible<Self::Ok, Error>;
-    type SerializeTuple = ser::Impossible<Self::Ok, Error>;
-    type SerializeTupleStruct = ser::Impossible<Self::Ok, Error>;
+    type SerializeSeq = NestedSeqSerializer<'a>;
+    type SerializeTuple = NestedSeqSerializer<'a>;
+    type SerializeTupleStruct = NestedSeqSerializer<'a>;
     type SerializeTupleVariant = ser::Impossible<Self::Ok, Error>;
     type SerializeMap = ser::Impossible<Self::Ok, Error>;
     type SerializeStruct = ser::Impossible<Self::Ok, Error>;
@@ -99,29 +304,31 @@ where
     serialize_float!(f64, serialize_f64);
 
     fn serialize_bool(self, value: bool) -> Result<Self::Ok> {
-        self.container
-            .push(Cow::Borrowed(if value { "true" } else { "false" }));
+        self.push_raw(if value { "true" } else { "false" });
         Ok(())
     }
     fn serialize_char(self, value: char) -> Result<Self::Ok> {
-        self.container.push(Cow::Owned(value.to_string()));
-        Ok(())
+        let mut buf = [0; 4];
+        self.serialize_str(value.encode_utf8(&mut buf))
     }
 
     fn serialize_str(self, value: &str) -> Result<Self::Ok> {
-        self.container.push(Cow::Owned(value.to_owned()));
+        self.push_str(value);
         Ok(())
     }
 
     fn serialize_unit(self) -> Result<Self::Ok> {
+        self.push_raw(sequence::NONE);
         Ok(())
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        self.push_raw(sequence::NONE);
         Ok(())
     }
 
     fn serialize_none(self) -> Result<Self::Ok> {
+        self.push_raw(sequence::NONE);
         Ok(())
     }
 
@@ -162,17 +369,17 @@ where
         Err(Error::custom("value only supports primitive"))
     }
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        Err(Error::custom("value only supports primitive"))
+        Ok(NestedSeqSerializer::new(self))
     }
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
-        Err(Error::custom("value only supports primitive"))
+        Ok(NestedSeqSerializer::new(self))
     }
     fn serialize_tuple_struct(
         self,
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        Err(Error::custom("value only supports primitive"))
+        Ok(NestedSeqSerializer::new(self))
     }
     fn serialize_tuple_variant(
         self,
@@ -1,37 +1,74 @@
 use crate::{
     error::{Error, Result},
-    ser::keyvalue::KeyValueSerializer,
+    ser::{config::SerializerConfig, encoder::Sink, keyvalue::KeyValueSerializer},
 };
-use form_urlencoded::Target;
 use serde::{de::Error as _, ser, Serialize};
-use std::borrow::Cow;
 
 use super::seq::SeqSerializer;
 
+/// Buffers reused across the fields of a serialized value.
+#[derive(Default)]
+pub struct Buffers {
+    /// The `.`-separated path of the field being serialized, extended and truncated one key at a time.
+    key: String,
+    /// The value of the sequence being serialized.
+    value: String,
+}
+
 /// `TopLevelSerializer` takes struct or map and serialize it.
-pub struct TopLevelSerializer<'input, 'output, T>
+pub struct TopLevelSerializer<'output, S>
 where
-    T: Target,
+    S: Sink,
 {
-    encoder: &'output mut form_urlencoded::Serializer<'input, T>,
+    encoder: &'output mut S,
+    buffers: &'output mut Buffers,
     state: State,
+    config: SerializerConfig,
 }
 
 enum State {
     Init,
+    /// Waiting for the keys of a struct, nested under the path in the key buffer if it is not empty.
     WaitingForKey,
-    WaitingForChildKey(Cow<'static, str>),
-    WaitingForValue(Cow<'static, str>),
+    /// Waiting for the value of the field whose path is in the key buffer.
+    WaitingForValue,
 }
 
-impl<'input, 'output, T> TopLevelSerializer<'input, 'output, T>
+impl<'output, S> TopLevelSerializer<'output, S>
 where
-    T: Target,
+    S: Sink,
 {
-    pub fn new(encoder: &'output mut form_urlencoded::Serializer<'input, T>) -> Self {
+    pub fn new(
+        encoder: &'output mut S,
+        buffers: &'output mut Buffers,
+        config: SerializerConfig,
+    ) -> Self {
         Self {
             encoder,
+            buffers,
             state: State::Init,
+            config,
+        }
+    }
+
+    /// Serialize the value as the value of `prefix`, or as a top-level struct if `prefix` is empty.
+    pub fn with_prefix(
+        encoder: &'output mut S,
+        buffers: &'output mut Buffers,
+        config: SerializerConfig,
+        prefix: &str,
+    ) -> Self {
+        buffers.key.clear();
+        buffers.key.push_str(prefix);
+        Self {
+            encoder,
+            buffers,
+            state: if prefix.is_empty() {
+                State::Init
+            } else {
+                State::WaitingForValue
+            },
+            config,
         }
     }
 }
@@ -42,9 +79,8 @@ macro_rules! serialize_primitive {
             match self.state {
                 State::Init => Err(Error::custom("top-level serializer supports only struct")),
                 State::WaitingForKey => Err(Error::custom("key not found")),
-                State::WaitingForChildKey(_) => Err(Error::custom("child key not found")),
-                State::WaitingForValue(key) => {
-                    let serializer = KeyValueSerializer::new(self.encoder, key);
+                State::WaitingForValue => {
+                    let serializer = KeyValueSerializer::new(self.encoder, &self.buffers.key);
                     value.serialize(serializer)
                 }
             }
@@ -52,18 +88,18 @@ macro_rules! serialize_primitive {
     };
 }
 
-impl<'input, 'output, T> ser::Serializer for TopLevelSerializer<'input, 'output, T>
+impl<'output, S> ser::Serializer for TopLevelSerializer<'output, S>
 where
-    T: 'output + Target,
+    S: 'output + Sink,
 {
-    type Ok = &'output mut form_urlencoded::Serializer<'input, T>;
+    type Ok = &'output mut S;
     type Error = Error;
 
     // type SerializeSeq = ser::Impossible<Self::Ok, Error>;
-    type SerializeSeq = SeqSerializer<'input, 'output, T>;
+    type SerializeSeq = SeqSerializer<'output, S>;
 
-    type SerializeTuple = ser::Impossible<Self::Ok, Error>;
-    type SerializeTupleStruct = ser::Impossible<Self::Ok, Error>;
+    type SerializeTuple = SeqSerializer<'output, S>;
+    type SerializeTupleStruct = SeqSerializer<'output, S>;
     type SerializeTupleVariant = ser::Impossible<Self::Ok, Error>;
     // TODO: Adapt this to handle map serialization.
     type SerializeMap = ser::Impossible<Self::Ok, Error>;
@@ -72,22 +108,12 @@ where
 
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
         match self.state {
-            State::Init => {
-                // Serialize the top-level struct
-                Ok(Self {
-                    encoder: self.encoder,
-                    state: State::WaitingForKey,
-                })
-            }
-            State::WaitingForChildKey(_) => Err(Error::custom("unexpected state")),
+            // Serialize the top-level struct, or the field that has a struct as a value
+            State::Init | State::WaitingForValue => Ok(Self {
+                state: State::WaitingForKey,
+                ..self
+            }),
             State::WaitingForKey => Err(Error::custom("the key has not yet provided")),
-            State::WaitingForValue(key) => {
-                // Serialize the field that has a struct as a value
-                Ok(Self {
-                    encoder: self.encoder,
-                    state: State::WaitingForChildKey(key),
-                })
-            }
         }
     }
 
@@ -152,24 +178,31 @@ where
     {
         Err(Error::custom("top-level serializer supports only struct"))
     }
-    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
         match self.state {
             State::Init => Err(Error::custom("top-level serializer supports only struct")),
-            State::WaitingForChildKey(_) => Err(Error::custom("unexpected state")),
             State::WaitingForKey => Err(Error::custom("the key has not yet provided")),
-            State::WaitingForValue(key) => Ok(SeqSerializer::new(self.encoder, key, len)),
+            State::WaitingForValue => {
+                let Buffers { key, value } = self.buffers;
+                Ok(SeqSerializer::new(
+                    self.encoder,
+                    key,
+                    self.config.separators,
+                    value,
+                ))
+            }
         }
         // Err(Error::custom("top-level serializer supports only struct"))
     }
-    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
-        Err(Error::custom("top-level serializer supports only struct"))
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
     }
     fn serialize_tuple_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        Err(Error::custom("top-level serializer supports only struct"))
+        self.serialize_seq(Some(len))
     }
     fn serialize_tuple_variant(
         self,
@@ -194,11 +227,11 @@ where
     }
 }
 
-impl<'input, 'output, T> ser::SerializeStruct for TopLevelSerializer<'input, 'output, T>
+impl<'output, S> ser::SerializeStruct for TopLevelSerializer<'output, S>
 where
-    T: 'output + Target,
+    S: 'output + Sink,
 {
-    type Ok = &'output mut form_urlencoded::Serializer<'input, T>;
+    type Ok = &'output mut S;
     type Error = Error;
 
     fn serialize_field<U>(
@@ -212,24 +245,23 @@ where
         match &self.state {
             State::Init => Err(Error::custom("unexpected field")),
             State::WaitingForKey => {
+                // Extend the path of the parent with the key, and restore it once the value is written.
+                let len = self.buffers.key.len();
+                if len > 0 {
+                    self.buffers.key.push('.');
+                }
+                self.buffers.key.push_str(key);
                 let serializer = TopLevelSerializer {
-                    encoder: self.encoder,
-                    state: State::WaitingForValue(Cow::Borrowed(key)),
-                };
-                value.serialize(serializer)?;
-                Ok(())
-            }
-            State::WaitingForChildKey(parent_key) => {
-                // Concatenate parent-key and child-key
-                let key = format!("{}.{}", parent_key, key);
-                let serializer = TopLevelSerializer {
-                    encoder: self.encoder,
-                    state: State::WaitingForValue(Cow::Owned(key)),
+                    encoder: &mut *self.encoder,
+                    buffers: &mut *self.buffers,
+                    state: State::WaitingForValue,
+                    config: self.config,
                 };
-                value.serialize(serializer)?;
-                Ok(())
+                let result = value.serialize(serializer).map(|_| ());
+                self.buffers.key.truncate(len);
+                result
             }
-            State::WaitingForValue(_) => Err(Error::custom("unexpected key and value")),
+            State::WaitingForValue => Err(Error::custom("unexpected key and value")),
         }
     }
 
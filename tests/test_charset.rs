@@ -0,0 +1,131 @@
+#![cfg(feature = "charset")]
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+    use serde_structuredqs::{encoding_rs, Charset, DeserializerConfig, SerializerConfig};
+
+    #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+    struct Person {
+        name: String,
+        city: String,
+    }
+
+    #[test]
+    fn deserialize_with_charset() {
+        let config = DeserializerConfig::new().charset(Charset::Encoding(encoding_rs::SHIFT_JIS));
+        let actual: Person = config
+            .deserialize_str("name=%8A%BF%8E%9A&city=Tokyo")
+            .unwrap();
+        assert_eq!(
+            actual,
+            Person {
+                name: String::from("漢字"),
+                city: String::from("Tokyo"),
+            }
+        );
+
+        let config = DeserializerConfig::new().charset(Charset::for_label(b"EUC-JP").unwrap());
+        let actual: Person = config
+            .deserialize_str("name=a+b&city=%C5%EC%B5%FE")
+            .unwrap();
+        assert_eq!(actual.city, "東京");
+
+        let config = DeserializerConfig::new().charset(Charset::for_label(b"latin1").unwrap());
+        let actual: Person = config.deserialize_str("name=caf%E9&city=").unwrap();
+        assert_eq!(actual.name, "café");
+
+        let config = DeserializerConfig::new().charset(Charset::Encoding(encoding_rs::EUC_JP));
+        assert!(matches!(
+            config.deserialize_str::<Person>("name=%FF%FF&city=x"),
+            Err(serde_structuredqs::Error::Parse(_, 5))
+        ));
+    }
+
+    #[test]
+    fn deserialize_with_charset_parameter() {
+        let config = DeserializerConfig::new().charset_parameter(true);
+        let actual: Person = config
+            .deserialize_str("name=%C5%EC%B5%FE&_charset_=EUC-JP&city=%C5%EC%B5%FE")
+            .unwrap();
+        assert_eq!(actual.name, "東京");
+        assert_eq!(actual.city, "東京");
+
+        let actual: Person = config
+            .deserialize_str("_charset_=UTF-8&name=%E6%9D%B1&city=x")
+            .unwrap();
+        assert_eq!(actual.name, "東");
+
+        assert!(matches!(
+            config.deserialize_str::<Person>("_charset_=klingon&name=a&city=b"),
+            Err(serde_structuredqs::Error::Parse(_, 10))
+        ));
+
+        // The parameter is ignored unless enabled.
+        let config = DeserializerConfig::new().charset(Charset::Utf8Lossy);
+        let actual: Person = config
+            .deserialize_str("_charset_=EUC-JP&name=%C5%EC&city=x")
+            .unwrap();
+        assert_eq!(actual.name, "\u{FFFD}\u{FFFD}");
+    }
+
+    #[test]
+    fn serialize_with_charset() {
+        let person = Person {
+            name: String::from("漢字 😀"),
+            city: String::from("café"),
+        };
+
+        let actual = SerializerConfig::new()
+            .charset(encoding_rs::SHIFT_JIS)
+            .to_string(&person)
+            .unwrap();
+        assert_eq!(
+            actual,
+            "name=%8A%BF%8E%9A+%26%23128512%3B&city=caf%26%23233%3B"
+        );
+
+        let actual = SerializerConfig::new()
+            .charset(encoding_rs::WINDOWS_1252)
+            .encoding(serde_structuredqs::Encoding::None)
+            .to_string(&Person {
+                name: String::from("a"),
+                city: String::from("café"),
+            })
+            .unwrap();
+        assert_eq!(actual, "name=a&city=caf%E9");
+
+        let encoded = SerializerConfig::new()
+            .charset(encoding_rs::EUC_JP)
+            .to_string(&Person {
+                name: String::from("東京"),
+                city: String::from("大阪"),
+            })
+            .unwrap();
+        let config = DeserializerConfig::new().charset(Charset::Encoding(encoding_rs::EUC_JP));
+        let actual: Person = config.deserialize_str(&encoded).unwrap();
+        assert_eq!(actual.name, "東京");
+        assert_eq!(actual.city, "大阪");
+    }
+
+    #[test]
+    fn canonicalize_with_charset() {
+        let person = Person {
+            name: String::from("漢字"),
+            city: String::from("東京"),
+        };
+        let config = SerializerConfig::new()
+            .charset(encoding_rs::SHIFT_JIS)
+            .canonical(true);
+        let canonical = config.to_string(&person).unwrap();
+        assert_eq!(canonical, "city=%93%8C%8B%9E&name=%8A%BF%8E%9A");
+        assert_eq!(config.canonicalize(&canonical).unwrap(), canonical);
+        assert_eq!(
+            config
+                .canonicalize("name=%8a%bf%8e%9a&city=%93%8c%8b%9e")
+                .unwrap(),
+            canonical
+        );
+        assert!(serde_structuredqs::canonicalize(&canonical).is_err());
+    }
+}
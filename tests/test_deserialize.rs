@@ -127,4 +127,781 @@ mod test {
         let actual: MyStruct = serde_structuredqs::from_str("a=&b=").unwrap();
         assert_eq!(actual, expected)
     }
+
+    #[test]
+    fn deserialize_nested_vec() {
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct MyStruct {
+            matrix: Vec<Vec<i32>>,
+            ranges: Vec<(u32, u32)>,
+            point: (i32, i32),
+        }
+
+        let expected = MyStruct {
+            matrix: vec![vec![1, 2], vec![3, 4]],
+            ranges: vec![(1, 5), (10, 20)],
+            point: (3, -4),
+        };
+        let actual: MyStruct =
+            serde_structuredqs::from_str("matrix=1,2;3,4&ranges=1,5;10,20&point=3,-4").unwrap();
+        assert_eq!(actual, expected)
+    }
+
+    #[test]
+    fn deserialize_with_case_insensitive_keys() {
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct SearchParams {
+            keyword: String,
+            page_size: Option<u32>,
+        }
+
+        let config = serde_structuredqs::DeserializerConfig::new()
+            .key_matching(serde_structuredqs::KeyMatching::CaseInsensitive);
+        let actual: SearchParams = config.deserialize_str("KEYWORD=foo&Page_Size=20").unwrap();
+        assert_eq!(
+            actual,
+            SearchParams {
+                keyword: String::from("foo"),
+                page_size: Some(20),
+            }
+        );
+        assert!(config
+            .deserialize_str::<SearchParams>("keyword=foo&pageSize=20")
+            .unwrap()
+            .page_size
+            .is_none());
+    }
+
+    #[test]
+    fn deserialize_with_style_insensitive_nested_keys() {
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct SearchParams {
+            page_size: u32,
+            filter: Option<FilteringParameter>,
+        }
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct FilteringParameter {
+            min_difficulty: i32,
+        }
+
+        let config = serde_structuredqs::DeserializerConfig::new()
+            .key_matching(serde_structuredqs::KeyMatching::StyleInsensitive);
+        let actual: SearchParams = config
+            .deserialize_str("pageSize=20&Filter.min-difficulty=800")
+            .unwrap();
+        assert_eq!(
+            actual,
+            SearchParams {
+                page_size: 20,
+                filter: Some(FilteringParameter {
+                    min_difficulty: 800
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_with_colliding_normalized_keys() {
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct SearchParams {
+            page_size: u32,
+        }
+
+        let config = serde_structuredqs::DeserializerConfig::new()
+            .key_matching(serde_structuredqs::KeyMatching::StyleInsensitive);
+        assert!(config
+            .deserialize_str::<SearchParams>("page_size=20&pageSize=30")
+            .is_err());
+    }
+
+    #[test]
+    fn deserialize_with_limits() {
+        use serde_structuredqs::{DeserializerConfig, Error};
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct MyStruct {
+            a: Option<Vec<i32>>,
+            b: Option<String>,
+            c: Option<Child>,
+        }
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct Child {
+            d: Option<i32>,
+        }
+
+        let config = DeserializerConfig::new()
+            .max_depth(2)
+            .max_pairs(2)
+            .max_key_len(5)
+            .max_value_len(7)
+            .max_sequence_len(3)
+            .max_input_len(20);
+
+        assert!(config.deserialize_str::<MyStruct>("a=1,2,3&c.d=1").is_ok());
+        let cases = [
+            ("a=1&b=2&c.d=3", "number of pairs"),
+            ("b=foo&c.d.e=1", "depth"),
+            ("bbbbbb=foo", "key length"),
+            ("b=foobarba", "value length"),
+            ("a=1,2,3,4", "sequence length"),
+            ("b=foo&b=foo&b=foo&b=foo", "input length"),
+        ];
+        for (input, expected) in cases {
+            match config.deserialize_str::<MyStruct>(input) {
+                Err(Error::LimitExceeded(limit, _)) => assert_eq!(limit, expected),
+                other => panic!("unexpected result for {}: {:?}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn deserialize_deeply_nested_key() {
+        #[derive(Debug, Deserialize)]
+        struct MyStruct {
+            #[allow(dead_code)]
+            a: Option<i32>,
+        }
+
+        let input = format!("{}=1", vec!["a"; 200_000].join("."));
+        assert!(matches!(
+            serde_structuredqs::from_str::<MyStruct>(&input),
+            Err(serde_structuredqs::Error::LimitExceeded("depth", _))
+        ));
+    }
+
+    #[test]
+    fn deserialize_keys_without_value() {
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct MyStruct {
+            a: Option<String>,
+            b: Option<String>,
+            c: Option<Child>,
+        }
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct Child {
+            d: Option<String>,
+        }
+
+        let expected = MyStruct {
+            a: Some(String::from("1")),
+            b: None,
+            c: Some(Child { d: None }),
+        };
+        for source in ["a=1&b&c.d", "b&a=1&c.d", ".a=1&&b=&c..d.", "=x&a=1&c.d"] {
+            let actual: MyStruct = serde_structuredqs::from_str(source).unwrap();
+            assert_eq!(actual, expected, "{}", source)
+        }
+    }
+
+    #[test]
+    fn deserialize_error_position() {
+        #[derive(Debug, Deserialize)]
+        struct MyStruct {
+            #[allow(dead_code)]
+            a: Option<u32>,
+            #[allow(dead_code)]
+            b: Option<u32>,
+        }
+
+        let cases = [("a=1&b=foo", 6), ("a=1&b=2&b=3", 10), ("a=1&b=%FF", 6)];
+        for (source, expected) in cases {
+            match serde_structuredqs::from_str::<MyStruct>(source) {
+                Err(serde_structuredqs::Error::Parse(_, position)) => {
+                    assert_eq!(position, expected, "{}", source)
+                }
+                other => panic!("unexpected result for {}: {:?}", source, other),
+            }
+        }
+    }
+
+    #[test]
+    fn deserialize_in_strict_mode() {
+        use std::collections::HashMap;
+
+        let config = serde_structuredqs::DeserializerConfig::new().strict(true);
+        assert!(config
+            .deserialize_str::<HashMap<String, String>>("a=1&b=%41+B&c")
+            .is_ok());
+
+        let cases = [
+            ("a=1&&b=2", 4),
+            ("&a=1", 0),
+            ("a=1&", 4),
+            ("=1", 0),
+            (".a=1", 0),
+            ("a.=1", 2),
+            ("a..b=1", 2),
+            ("a.", 2),
+            ("a==1", 2),
+            ("a=%zz", 2),
+            ("a=%4", 2),
+        ];
+        for (source, expected) in cases {
+            match config.deserialize_str::<HashMap<String, String>>(source) {
+                Err(serde_structuredqs::Error::Parse(_, position)) => {
+                    assert_eq!(position, expected, "{}", source)
+                }
+                other => panic!("unexpected result for {}: {:?}", source, other),
+            }
+        }
+    }
+
+    #[test]
+    fn deserialize_with_literal_plus() {
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct MyStruct<'a> {
+            tz: &'a str,
+            q: String,
+            tags: Vec<String>,
+        }
+
+        let config = serde_structuredqs::DeserializerConfig::new().plus_as_space(false);
+        let actual: MyStruct = config
+            .deserialize_str("tz=+09:00&q=c+%2B+c&tags=a+b,c%20d")
+            .unwrap();
+        assert_eq!(
+            actual,
+            MyStruct {
+                tz: "+09:00",
+                q: String::from("c+++c"),
+                tags: vec![String::from("a+b"), String::from("c d")],
+            }
+        );
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct Owned {
+            tz: String,
+        }
+
+        let actual: Owned = serde_structuredqs::from_str("tz=+09:00").unwrap();
+        assert_eq!(actual.tz, " 09:00");
+    }
+
+    #[test]
+    fn deserialize_lossy_utf8() {
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct MyStruct {
+            q: String,
+            tags: Vec<String>,
+        }
+
+        let source = "q=caf%E9&tags=%FFa,b";
+        assert!(matches!(
+            serde_structuredqs::from_str::<MyStruct>(source),
+            Err(serde_structuredqs::Error::Parse(_, 2))
+        ));
+
+        let config = serde_structuredqs::DeserializerConfig::new()
+            .charset(serde_structuredqs::Charset::Utf8Lossy);
+        let actual: MyStruct = config.deserialize_bytes(b"q=caf\xE9&tags=%FFa,b").unwrap();
+        assert_eq!(
+            actual,
+            MyStruct {
+                q: String::from("caf\u{FFFD}"),
+                tags: vec![String::from("\u{FFFD}a"), String::from("b")],
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_borrowed() {
+        use std::{borrow::Cow, collections::HashMap};
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct MyStruct<'a> {
+            #[serde(borrow)]
+            words: Vec<&'a str>,
+            #[serde(borrow)]
+            matrix: Vec<Vec<&'a str>>,
+            #[serde(borrow)]
+            map: HashMap<&'a str, &'a str>,
+            #[serde(borrow)]
+            cow: Cow<'a, str>,
+            cows: Vec<Cow<'a, str>>,
+        }
+
+        let source = "words=foo,,bar,\\e&matrix=1,2;3&map.key=value&cow=x&cows=a%20b,c\\,d";
+        let actual: MyStruct = serde_structuredqs::from_str(source).unwrap();
+        assert_eq!(actual.words, vec!["foo", "bar", ""]);
+        assert_eq!(actual.matrix, vec![vec!["1", "2"], vec!["3"]]);
+        assert_eq!(actual.map, HashMap::from([("key", "value")]));
+        assert!(matches!(actual.cow, Cow::Borrowed("x")));
+        assert_eq!(actual.cows, vec!["a b", "c,d"]);
+
+        // An escaped or percent-encoded element cannot be borrowed.
+        for source in [
+            "words=a\\,b&matrix=&map.k=v&cow=&cows=",
+            "words=a%20b&matrix=&map.k=v&cow=&cows=",
+        ] {
+            assert!(
+                serde_structuredqs::from_str::<MyStruct>(source).is_err(),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn deserialize_value_and_nested_keys() {
+        use std::collections::HashMap;
+
+        #[derive(Debug, Deserialize)]
+        struct MyStruct {
+            #[allow(dead_code)]
+            a: HashMap<String, String>,
+            #[allow(dead_code)]
+            b: Option<String>,
+        }
+
+        let cases = [("a=1&a.b=2", 8), ("a.b=2&a=1", 8), ("a.b=2&a.b.c=1", 12)];
+        for (source, expected) in cases {
+            match serde_structuredqs::from_str::<MyStruct>(source) {
+                Err(serde_structuredqs::Error::Parse(_, position)) => {
+                    assert_eq!(position, expected, "{}", source)
+                }
+                other => panic!("unexpected result for {}: {:?}", source, other),
+            }
+        }
+
+        // A value is only decoded when it is deserialized.
+        let actual: MyStruct = serde_structuredqs::from_str("a.b=1&c=%FF").unwrap();
+        assert_eq!(
+            actual.a,
+            HashMap::from([(String::from("b"), String::from("1"))])
+        );
+    }
+
+    #[test]
+    fn deserialize_percent_signs() {
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct MyStruct<'a> {
+            rate: &'a str,
+            partial: String,
+            mixed: String,
+        }
+
+        let actual: MyStruct =
+            serde_structuredqs::from_str("rate=100%&partial=%4&mixed=%zz+%41%2b%").unwrap();
+        assert_eq!(
+            actual,
+            MyStruct {
+                rate: "100%",
+                partial: String::from("%4"),
+                mixed: String::from("%zz A+%"),
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_incrementally() {
+        use serde_structuredqs::{DeserializerConfig, Error, IncrementalParser};
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct MyStruct {
+            a: Option<Vec<i32>>,
+            b: Option<String>,
+            c: Option<Child>,
+        }
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct Child {
+            d: Option<i32>,
+        }
+
+        let inputs = [
+            "a=1%2C2,3&b=%E3%81%BB+%E3%81%92&c.d=4",
+            "b=x&&c..d=1&=2&b",
+            "c.d=1&c=2",
+        ];
+        for input in inputs {
+            let expected = serde_structuredqs::from_str::<MyStruct>(input);
+            for chunk_len in 1..=input.len() {
+                let mut parser = IncrementalParser::new(DeserializerConfig::new());
+                for chunk in input.as_bytes().chunks(chunk_len) {
+                    parser.push(chunk).unwrap();
+                }
+                let actual = parser.finish::<MyStruct>();
+                assert_eq!(format!("{:?}", actual), format!("{:?}", expected));
+            }
+        }
+
+        let config = DeserializerConfig::new()
+            .max_depth(2)
+            .max_pairs(2)
+            .max_key_len(5)
+            .max_value_len(7)
+            .max_sequence_len(3)
+            .max_input_len(20);
+        let cases = [
+            ("a=1&b=2&c.d=3", "number of pairs", 8),
+            ("b=foo&c.d.e=1", "depth", 9),
+            ("bbbbbb=foo", "key length", 5),
+            ("b=foobarba", "value length", 9),
+            ("a=1,2,3,4", "sequence length", 9),
+        ];
+        for (input, expected, failing_chunk) in cases {
+            let mut parser = IncrementalParser::new(config);
+            let chunks = input.as_bytes().chunks(1).enumerate();
+            let result = chunks
+                .map(|(i, chunk)| parser.push(chunk).map_err(|e| (i, e)))
+                .find_map(Result::err);
+            match result {
+                Some((i, Error::LimitExceeded(limit, _))) => {
+                    assert_eq!((limit, i), (expected, failing_chunk))
+                }
+                None => match parser.finish::<MyStruct>() {
+                    Err(Error::LimitExceeded(limit, _)) => assert_eq!(limit, expected),
+                    other => panic!("unexpected result for {}: {:?}", input, other),
+                },
+                other => panic!("unexpected result for {}: {:?}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn deserialize_from_reader() {
+        use serde_structuredqs::{DeserializerConfig, Error};
+        use std::io::Read;
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct MyStruct {
+            a: Vec<String>,
+            b: String,
+        }
+
+        let reader = b"a=x%2".chain(&b"0,y&b="[..]).chain(&b"z"[..]);
+        assert_eq!(
+            serde_structuredqs::from_reader::<MyStruct, _>(reader).unwrap(),
+            MyStruct {
+                a: vec![String::from("x "), String::from("y")],
+                b: String::from("z"),
+            }
+        );
+
+        // An endless body fails once it exceeds the limit.
+        let config = DeserializerConfig::new().max_input_len(1 << 16);
+        assert!(matches!(
+            config.deserialize_reader::<MyStruct, _>(std::io::repeat(b'a')),
+            Err(Error::LimitExceeded("input length", _))
+        ));
+    }
+
+    #[test]
+    fn deserialize_from_pairs() {
+        use serde_structuredqs::{DeserializerConfig, Error};
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct MyStruct {
+            q: String,
+            tags: Vec<String>,
+            child: Child,
+        }
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct Child {
+            a: Option<i32>,
+            b: Option<String>,
+        }
+
+        let pairs = vec![
+            (String::from("child.a"), String::from("1")),
+            (String::from("q"), String::from("a+b%20c&d=e")),
+            (String::from("tags"), String::from("x,y\\,z")),
+        ];
+        let expected = MyStruct {
+            q: String::from("a+b%20c&d=e"),
+            tags: vec![String::from("x"), String::from("y,z")],
+            child: Child {
+                a: Some(1),
+                b: None,
+            },
+        };
+        assert_eq!(
+            serde_structuredqs::from_pairs::<MyStruct, _, _, _>(pairs).unwrap(),
+            expected
+        );
+
+        // The pairs of `to_pairs` are deserialized into the same value as the query-string.
+        let pairs = [("q", "x"), ("tags", ","), ("child.b", "")];
+        assert_eq!(
+            serde_structuredqs::from_pairs::<MyStruct, _, _, _>(pairs).unwrap(),
+            serde_structuredqs::from_str::<MyStruct>("q=x&tags=%2C&child.b=").unwrap()
+        );
+
+        let pairs = [("q", "x"), ("q", "y"), ("tags", "")];
+        assert!(matches!(
+            serde_structuredqs::from_pairs::<MyStruct, _, _, _>(pairs),
+            Err(Error::Parse(_, 6))
+        ));
+
+        let config = DeserializerConfig::new().max_depth(1);
+        assert!(matches!(
+            config.deserialize_pairs::<MyStruct, _, _, _>([("child.a", "1")]),
+            Err(Error::LimitExceeded("depth", 1))
+        ));
+    }
+
+    #[test]
+    fn deserialize_parsed_query() {
+        use serde_structuredqs::{DeserializerConfig, Error};
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct Pagination {
+            page: u32,
+            per_page: Option<u32>,
+        }
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct Filters<'a> {
+            category: &'a str,
+            price: Option<Range>,
+        }
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct Range {
+            min: Option<u32>,
+            max: Option<u32>,
+        }
+
+        let input = "page=2&filter.category=books&filters_long_name.category=x\
+            &filter.price.min=10&filter=&filters.category=y&filter.price.max=20";
+        let query = DeserializerConfig::new().parse_str(input).unwrap();
+
+        // Only the keys that are deserialized are checked.
+        assert!(query.deserialize::<Pagination>().is_ok());
+        assert!(query.deserialize_at::<Filters>("filter").is_err());
+
+        let query = serde_structuredqs::ParsedQuery::parse(
+            "page=2&filter.category=books&filters_long_name.category=x\
+            &filter.price.min=10&filters.category=y&filter.price.max=20",
+        )
+        .unwrap();
+        assert_eq!(
+            query.deserialize::<Pagination>().unwrap(),
+            Pagination {
+                page: 2,
+                per_page: None
+            }
+        );
+        let expected = Filters {
+            category: "books",
+            price: Some(Range {
+                min: Some(10),
+                max: Some(20),
+            }),
+        };
+        assert_eq!(query.deserialize_at::<Filters>("filter").unwrap(), expected);
+        assert_eq!(
+            query.deserialize_at::<Filters>("filters").unwrap(),
+            Filters {
+                category: "y",
+                price: None
+            }
+        );
+        assert_eq!(
+            query.deserialize_at::<Range>("filter.price").unwrap(),
+            Range {
+                min: Some(10),
+                max: Some(20)
+            }
+        );
+        assert!(matches!(
+            query.deserialize_at::<Range>("filter.missing"),
+            Err(Error::Missing(path)) if path == "filter.missing"
+        ));
+        assert_eq!(
+            query
+                .deserialize_at::<Option<Range>>("filter.missing")
+                .unwrap(),
+            None
+        );
+        assert_eq!(query.deserialize_at::<u32>("page").unwrap(), 2);
+        assert!(query.deserialize_at::<Range>("page").is_err());
+
+        // Errors point into the original input.
+        let query = serde_structuredqs::ParsedQuery::parse("a.page=1&a.page=2").unwrap();
+        assert!(matches!(
+            query.deserialize_at::<Pagination>("a"),
+            Err(Error::Parse(_, 16))
+        ));
+    }
+
+    #[test]
+    fn deserialize_at_path() {
+        use serde_structuredqs::Error;
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct WidgetParams<'a> {
+            theme: &'a str,
+            size: Option<u32>,
+        }
+
+        let query = "w.theme=dark&w.size=3&page=2&x.y.theme=light&w2.theme=none";
+        assert_eq!(
+            serde_structuredqs::from_str_at::<WidgetParams>("w", query).unwrap(),
+            WidgetParams {
+                theme: "dark",
+                size: Some(3)
+            }
+        );
+        assert_eq!(
+            serde_structuredqs::from_str_at::<Option<WidgetParams>>("x.y", query).unwrap(),
+            Some(WidgetParams {
+                theme: "light",
+                size: None
+            })
+        );
+        assert_eq!(
+            serde_structuredqs::from_str_at::<Option<WidgetParams>>("x.z", query).unwrap(),
+            None
+        );
+        assert!(matches!(
+            serde_structuredqs::from_str_at::<WidgetParams>("v", query),
+            Err(Error::Missing(path)) if path == "v"
+        ));
+        assert!(matches!(
+            serde_structuredqs::from_str_at::<WidgetParams>("v", ""),
+            Err(Error::Missing(_))
+        ));
+        assert_eq!(
+            serde_structuredqs::from_str_at::<u32>("page", query).unwrap(),
+            2
+        );
+        // An empty path is the whole query.
+        assert!(serde_structuredqs::from_str_at::<WidgetParams>("", "theme=a").is_ok());
+    }
+
+    #[test]
+    fn deserialize_in_place() {
+        use serde::de::{Deserializer, IgnoredAny, MapAccess, Visitor};
+        use std::fmt;
+
+        struct InPlace<'a, T>(&'a mut T);
+
+        impl<'de, 'a, T: Deserialize<'de>> serde::de::DeserializeSeed<'de> for InPlace<'a, T> {
+            type Value = ();
+
+            fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+                T::deserialize_in_place(deserializer, self.0)
+            }
+        }
+
+        // Overwrites only the fields whose keys are visited.
+        macro_rules! deserialize_in_place {
+            ($ty:ident { $($field:ident),* }) => {
+                impl<'de> Deserialize<'de> for $ty {
+                    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                        let mut place = Self::default();
+                        Self::deserialize_in_place(deserializer, &mut place)?;
+                        Ok(place)
+                    }
+
+                    fn deserialize_in_place<D: Deserializer<'de>>(
+                        deserializer: D,
+                        place: &mut Self,
+                    ) -> Result<(), D::Error> {
+                        struct Fields<'a>(&'a mut $ty);
+
+                        impl<'de, 'a> Visitor<'de> for Fields<'a> {
+                            type Value = ();
+
+                            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                                f.write_str(stringify!($ty))
+                            }
+
+                            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+                                while let Some(key) = map.next_key::<String>()? {
+                                    match key.as_str() {
+                                        $(stringify!($field) => map.next_value_seed(InPlace(&mut self.0.$field))?,)*
+                                        _ => {
+                                            map.next_value::<IgnoredAny>()?;
+                                        }
+                                    }
+                                }
+                                Ok(())
+                            }
+                        }
+
+                        let fields = &[$(stringify!($field)),*];
+                        deserializer.deserialize_struct(stringify!($ty), fields, Fields(place))
+                    }
+                }
+            };
+        }
+
+        #[derive(Debug, Default, PartialEq)]
+        struct Config {
+            name: String,
+            limit: u32,
+            tags: Vec<String>,
+            page: Page,
+            connections: usize,
+        }
+
+        #[derive(Debug, Default, PartialEq)]
+        struct Page {
+            size: u32,
+            number: u32,
+        }
+
+        deserialize_in_place!(Config {
+            name,
+            limit,
+            tags,
+            page
+        });
+        deserialize_in_place!(Page { size, number });
+
+        let mut config = Config {
+            name: String::from("default"),
+            limit: 20,
+            tags: vec![String::from("a"), String::from("b")],
+            page: Page {
+                size: 20,
+                number: 1,
+            },
+            connections: 8,
+        };
+        serde_structuredqs::from_str_in_place("limit=50&tags=c&page.number=3", &mut config)
+            .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                name: String::from("default"),
+                limit: 50,
+                tags: vec![String::from("c")],
+                page: Page {
+                    size: 20,
+                    number: 3
+                },
+                connections: 8,
+            }
+        );
+
+        assert!(serde_structuredqs::from_str_in_place("limit=x", &mut config).is_err());
+
+        // A derived type is replaced as a whole.
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Derived {
+            limit: u32,
+            #[serde(skip)]
+            connections: usize,
+        }
+
+        let mut derived = Derived {
+            limit: 20,
+            connections: 8,
+        };
+        serde_structuredqs::from_str_in_place("limit=50", &mut derived).unwrap();
+        assert_eq!(
+            derived,
+            Derived {
+                limit: 50,
+                connections: 0
+            }
+        );
+    }
 }
@@ -0,0 +1,162 @@
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+    use serde_structuredqs::{Encoding, MergeConfig, ScalarMerge, SequenceMerge, SerializerConfig};
+
+    fn readable() -> MergeConfig {
+        MergeConfig::new().serializer_config(SerializerConfig::new().encoding(Encoding::Readable))
+    }
+
+    #[test]
+    fn merge_scalars_and_nested_keys() {
+        let base = "sort=date&page.size=20&page.number=1&lang=ja";
+        let overlay = "page.number=3&q=rust&lang=en";
+
+        let cases = [
+            (
+                ScalarMerge::Override,
+                "lang=en&page.number=3&page.size=20&q=rust&sort=date",
+            ),
+            (
+                ScalarMerge::Keep,
+                "lang=ja&page.number=1&page.size=20&q=rust&sort=date",
+            ),
+        ];
+        for (scalars, expected) in cases {
+            let config = readable().scalars(scalars);
+            assert_eq!(config.merge_str(base, overlay).unwrap(), expected);
+        }
+
+        // A value and nested keys of the same key follow the scalar rule as a whole.
+        let cases = [
+            (ScalarMerge::Override, "filter.a=1&filter.b=2"),
+            (ScalarMerge::Keep, "filter=all"),
+        ];
+        for (scalars, expected) in cases {
+            let config = readable().scalars(scalars);
+            assert_eq!(
+                config
+                    .merge_str("filter=all", "filter.a=1&filter.b=2")
+                    .unwrap(),
+                expected
+            );
+        }
+
+        assert_eq!(
+            serde_structuredqs::merge("", "a=%26&b=").unwrap(),
+            "a=%26&b="
+        );
+    }
+
+    #[test]
+    fn merge_sequences() {
+        let cases = [
+            (SequenceMerge::Replace, "a,b", "b,c", "b,c"),
+            (SequenceMerge::Append, "a,b", "b,c", "a,b,b,c"),
+            (SequenceMerge::Union, "a,b", "b,c", "a,b,c"),
+            (SequenceMerge::Append, "a,b", "c", "a,b,c"),
+            (SequenceMerge::Append, ",", "c,d", "c,d"),
+            (SequenceMerge::Union, "a\\,b,c", "a,b,c", "a\\,b,c,a,b"),
+            (SequenceMerge::Append, "1,2;3,4", "5,6;", "1,2;3,4;5,6"),
+            (SequenceMerge::Union, "1,2;", "1,2;", "1,2;"),
+        ];
+        for (sequences, base, overlay, expected) in cases {
+            let config = readable().sequences(sequences);
+            assert_eq!(
+                config
+                    .merge_str(&format!("k={}", base), &format!("k={}", overlay))
+                    .unwrap(),
+                format!("k={}", expected)
+                    .replace('\\', "%5C")
+                    .replace(';', "%3B"),
+                "{:?} {} {}",
+                sequences,
+                base,
+                overlay
+            );
+        }
+
+        #[derive(Debug, Deserialize, Eq, PartialEq)]
+        struct Matrix {
+            rows: Vec<Vec<i32>>,
+        }
+        let config = MergeConfig::new().sequences(SequenceMerge::Append);
+        assert_eq!(
+            config
+                .merge_str_into::<Matrix>("rows=1,2;", "rows=3,4;")
+                .unwrap(),
+            Matrix {
+                rows: vec![vec![1, 2], vec![3, 4]]
+            }
+        );
+    }
+
+    #[test]
+    fn merge_value() {
+        #[derive(Serialize)]
+        struct Overrides {
+            page: Page,
+            q: Option<String>,
+            tags: Vec<String>,
+        }
+
+        #[derive(Serialize)]
+        struct Page {
+            number: u32,
+        }
+
+        let overrides = Overrides {
+            page: Page { number: 2 },
+            q: None,
+            tags: vec![String::from("b"), String::from("c,d")],
+        };
+        let config = readable().sequences(SequenceMerge::Union);
+        assert_eq!(
+            config
+                .merge_value("q=rust&page.size=20&tags=a,b", &overrides)
+                .unwrap(),
+            "page.number=2&page.size=20&q=rust&tags=a,b,c%5C,d"
+        );
+    }
+
+    #[test]
+    fn merge_invalid_queries() {
+        use serde_structuredqs::Error;
+
+        assert!(matches!(
+            serde_structuredqs::merge("a=1&a=2", "b=1"),
+            Err(Error::Parse(_, 6))
+        ));
+        assert!(matches!(
+            serde_structuredqs::merge("b=1", "a=1&a.b=2"),
+            Err(Error::Parse(_, _))
+        ));
+    }
+
+    #[test]
+    fn merge_keys_with_encoded_dots() {
+        use std::collections::HashMap;
+
+        // `a%2Eb` is a single segment, unlike `a.b`.
+        let merged = serde_structuredqs::merge("a%2Eb=1&a.c=2", "a%2Eb=3").unwrap();
+        assert_eq!(merged, "a.c=2&a%2Eb=3");
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Params {
+            a: HashMap<String, u32>,
+            #[serde(rename = "a.b")]
+            ab: u32,
+        }
+
+        let params: Params = MergeConfig::new()
+            .merge_str_into("a%2Eb=1&a.c=2", "a%2Eb=3")
+            .unwrap();
+        assert_eq!(
+            params,
+            Params {
+                a: HashMap::from([(String::from("c"), 2)]),
+                ab: 3,
+            }
+        );
+    }
+}
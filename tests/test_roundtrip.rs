@@ -0,0 +1,243 @@
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    fn roundtrip<T>(value: &T) -> T
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+    {
+        let encoded = serde_structuredqs::to_string(value).unwrap();
+        serde_structuredqs::from_str(&encoded).unwrap()
+    }
+
+    #[test]
+    fn roundtrip_vec_of_empty_strings() {
+        #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+        struct MyStruct {
+            a: Vec<String>,
+        }
+
+        let value = MyStruct {
+            a: vec![String::from(""), String::from("a"), String::from("")],
+        };
+        assert_eq!(roundtrip(&value), value);
+    }
+
+    #[test]
+    fn roundtrip_vec_of_options() {
+        #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+        struct MyStruct {
+            a: Vec<Option<i32>>,
+            b: Vec<Option<String>>,
+        }
+
+        let value = MyStruct {
+            a: vec![Some(1), None, Some(3)],
+            b: vec![None, Some(String::from("")), Some(String::from("x"))],
+        };
+        assert_eq!(roundtrip(&value), value);
+    }
+
+    #[test]
+    fn roundtrip_elements_containing_separator_and_escape() {
+        #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+        struct MyStruct {
+            a: Vec<String>,
+        }
+
+        let value = MyStruct {
+            a: vec![
+                String::from("a,b"),
+                String::from(","),
+                String::from("\\"),
+                String::from("\\e"),
+                String::from("\\0"),
+            ],
+        };
+        assert_eq!(roundtrip(&value), value);
+    }
+
+    #[test]
+    fn roundtrip_empty_vec() {
+        #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+        struct MyStruct {
+            a: Vec<i32>,
+            b: Option<Vec<i32>>,
+            c: Option<Vec<i32>>,
+        }
+
+        let value = MyStruct {
+            a: vec![],
+            b: Some(vec![]),
+            c: None,
+        };
+        assert_eq!(roundtrip(&value), value);
+    }
+
+    #[test]
+    fn roundtrip_nested_sequences() {
+        #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+        struct MyStruct {
+            matrix: Vec<Vec<i32>>,
+            ranges: Vec<(u32, u32)>,
+            single: Vec<Vec<i32>>,
+            hollow: Vec<Vec<i32>>,
+            cube: Vec<Vec<Vec<String>>>,
+        }
+
+        let value = MyStruct {
+            matrix: vec![vec![1, 2], vec![3, 4]],
+            ranges: vec![(1, 5), (10, 20)],
+            single: vec![vec![1, 2]],
+            hollow: vec![vec![], vec![1]],
+            cube: vec![
+                vec![vec![String::from("a;b"), String::from("")]],
+                vec![vec![String::from("c|d")], vec![]],
+            ],
+        };
+        assert_eq!(roundtrip(&value), value);
+    }
+
+    #[test]
+    fn roundtrip_with_every_decodable_encoding() {
+        use serde_structuredqs::{Encoding, SerializerConfig};
+
+        #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+        struct MyStruct {
+            q: String,
+            path: String,
+            tags: Vec<String>,
+        }
+
+        let value = MyStruct {
+            q: String::from("a b+c&d=e%f~g*h"),
+            path: String::from("/x:y,z"),
+            tags: vec![String::from("ほげ"), String::from("a,b")],
+        };
+        for encoding in [
+            Encoding::FormUrlencoded,
+            Encoding::Rfc3986,
+            Encoding::Readable,
+        ] {
+            let encoded = SerializerConfig::new()
+                .encoding(encoding)
+                .to_string(&value)
+                .unwrap();
+            assert_eq!(
+                serde_structuredqs::from_str::<MyStruct>(&encoded).unwrap(),
+                value,
+                "{}",
+                encoded
+            );
+        }
+    }
+
+    #[test]
+    fn roundtrip_diff_from_defaults() {
+        #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+        #[serde(default)]
+        struct Search {
+            q: Option<String>,
+            limit: u32,
+            sort: Option<String>,
+            tags: Vec<String>,
+            page: Page,
+            filter: Option<Filter>,
+        }
+
+        // `Page` has no defaults of its own, while the defaults of `Filter` differ from those of `Search`.
+        #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+        struct Page {
+            size: u32,
+            number: u32,
+        }
+
+        #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+        #[serde(default)]
+        struct Filter {
+            lang: String,
+            stars: Option<u32>,
+            range: Option<Range>,
+        }
+
+        #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+        struct Range {
+            min: u32,
+            max: u32,
+        }
+
+        impl Default for Filter {
+            fn default() -> Self {
+                Filter {
+                    lang: String::from("en"),
+                    stars: Some(1),
+                    range: Some(Range { min: 0, max: 9 }),
+                }
+            }
+        }
+
+        impl Default for Search {
+            fn default() -> Self {
+                Search {
+                    q: None,
+                    limit: 20,
+                    sort: Some(String::from("relevance")),
+                    tags: vec![String::from("a")],
+                    page: Page {
+                        size: 20,
+                        number: 1,
+                    },
+                    filter: Some(Filter {
+                        lang: String::from("rust"),
+                        stars: Some(10),
+                        range: Some(Range { min: 1, max: 5 }),
+                    }),
+                }
+            }
+        }
+
+        let defaults = Search::default();
+        assert_eq!(
+            serde_structuredqs::to_string_diff(&defaults, &defaults).unwrap(),
+            ""
+        );
+
+        let cases = [
+            // A nested struct is written as a whole, and a missing field as a single empty value.
+            (
+                Search {
+                    q: Some(String::from("rust")),
+                    sort: None,
+                    tags: vec![],
+                    page: Page {
+                        size: 20,
+                        number: 3,
+                    },
+                    filter: None,
+                    ..Default::default()
+                },
+                "q=rust&tags=%2C&page.size=20&page.number=3&filter=&sort=",
+            ),
+            // The parts of the defaults that a nested struct lacks are written with empty values.
+            (
+                Search {
+                    filter: Some(Filter {
+                        lang: String::from("rust"),
+                        stars: None,
+                        range: None,
+                    }),
+                    ..Default::default()
+                },
+                "filter.lang=rust&filter.range=&filter.stars=",
+            ),
+        ];
+        for (value, expected) in cases {
+            let encoded = serde_structuredqs::to_string_diff(&value, &defaults).unwrap();
+            assert_eq!(encoded, expected);
+            assert_eq!(
+                serde_structuredqs::from_str::<Search>(&encoded).unwrap(),
+                value
+            );
+        }
+    }
+}
@@ -4,6 +4,7 @@ mod test {
     use serde::Serialize;
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn flat_struct() {
         #[derive(Serialize)]
         struct FlatStruct {
@@ -146,7 +147,532 @@ mod test {
                 b: Some(vec![])
             })
             .unwrap(),
-            String::from("a=&b=")
+            String::from("a=%2C&b=%2C")
         )
     }
+
+    #[test]
+    fn serialize_vec_with_empty_and_none_elements() {
+        #[derive(Serialize)]
+        struct MyStruct {
+            a: Vec<String>,
+            b: Vec<Option<i32>>,
+        }
+
+        assert_eq!(
+            serde_structuredqs::to_string(&MyStruct {
+                a: vec![String::from(""), String::from("a,b"), String::from("c\\d")],
+                b: vec![Some(1), None, Some(3)],
+            })
+            .unwrap(),
+            String::from("a=%5Ce%2Ca%5C%2Cb%2Cc%5C%5Cd&b=1%2C%5C0%2C3")
+        )
+    }
+
+    #[test]
+    fn serialize_nested_vec() {
+        #[derive(Serialize)]
+        struct MyStruct {
+            matrix: Vec<Vec<i32>>,
+            ranges: Vec<(u32, u32)>,
+            single: Vec<Vec<i32>>,
+        }
+
+        let config = serde_structuredqs::SerializerConfig::new()
+            .separators(serde_structuredqs::Separators::new(&['-', '_']).unwrap());
+        assert_eq!(
+            config
+                .to_string(&MyStruct {
+                    matrix: vec![vec![1, 2], vec![3, 4]],
+                    ranges: vec![(1, 5)],
+                    single: vec![vec![]],
+                })
+                .unwrap(),
+            String::from("matrix=1-2_3-4&ranges=1-5_&single=-_")
+        )
+    }
+
+    #[test]
+    fn serialize_elements_of_different_depths() {
+        #[derive(Serialize)]
+        struct MyStruct {
+            pair: (i32, Vec<i32>),
+            deep: Vec<Vec<Vec<i32>>>,
+            offsets: Vec<i32>,
+        }
+
+        let config = serde_structuredqs::SerializerConfig::new()
+            .separators(serde_structuredqs::Separators::new(&['-', '_', '~']).unwrap())
+            .encoding(serde_structuredqs::Encoding::None);
+        assert_eq!(
+            config
+                .to_string(&MyStruct {
+                    pair: (1, vec![2, 3]),
+                    deep: vec![vec![vec![1]], vec![]],
+                    offsets: vec![-1, 2],
+                })
+                .unwrap(),
+            String::from("pair=1_2-3&deep=1_~-&offsets=\\-1-2")
+        )
+    }
+
+    #[test]
+    fn serialize_too_deeply_nested_vec() {
+        #[derive(Serialize)]
+        struct MyStruct {
+            a: Vec<Vec<i32>>,
+        }
+
+        let config = serde_structuredqs::SerializerConfig::new()
+            .separators(serde_structuredqs::Separators::new(&[',']).unwrap());
+        assert!(config.to_string(&MyStruct { a: vec![vec![1]] }).is_err())
+    }
+
+    #[test]
+    fn serialize_with_encoding() {
+        use serde_structuredqs::{Encoding, SerializerConfig};
+
+        #[derive(Serialize)]
+        struct MyStruct {
+            q: String,
+            ids: Vec<u32>,
+            url: String,
+        }
+
+        let value = MyStruct {
+            q: String::from("foo bar~"),
+            ids: vec![1, 2],
+            url: String::from("https://example.com/a?b=c"),
+        };
+        let cases = [
+            (
+                Encoding::FormUrlencoded,
+                "q=foo+bar%7E&ids=1%2C2&url=https%3A%2F%2Fexample.com%2Fa%3Fb%3Dc",
+            ),
+            (
+                Encoding::Rfc3986,
+                "q=foo%20bar~&ids=1%2C2&url=https%3A%2F%2Fexample.com%2Fa%3Fb%3Dc",
+            ),
+            (
+                Encoding::Readable,
+                "q=foo%20bar~&ids=1,2&url=https://example.com/a%3Fb%3Dc",
+            ),
+            (
+                Encoding::None,
+                "q=foo bar~&ids=1,2&url=https://example.com/a?b=c",
+            ),
+        ];
+        for (encoding, expected) in cases {
+            assert_eq!(
+                SerializerConfig::new()
+                    .encoding(encoding)
+                    .to_string(&value)
+                    .unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn serialize_into_buffers() {
+        #[derive(Serialize)]
+        struct Params {
+            q: String,
+            page: Option<u32>,
+        }
+
+        let value = Params {
+            q: String::from("a b"),
+            page: Some(2),
+        };
+
+        assert_eq!(serde_structuredqs::to_vec(&value).unwrap(), b"q=a+b&page=2");
+
+        // Each pair is written as soon as it is serialized.
+        struct Chunks(Vec<Vec<u8>>);
+
+        impl std::io::Write for Chunks {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.push(buf.to_vec());
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut writer = Chunks(Vec::new());
+        serde_structuredqs::to_writer(&value, &mut writer).unwrap();
+        assert_eq!(writer.0, [&b"q=a+b"[..], &b"&page=2"[..]]);
+
+        // A writer that runs out of space fails with its error.
+        let mut small = [0; 4];
+        assert!(matches!(
+            serde_structuredqs::to_writer(&value, &mut small[..]),
+            Err(serde_structuredqs::Error::Io(_))
+        ));
+
+        let cases = [
+            (&b""[..], &b"q=a+b&page=2"[..]),
+            (b"x=1", b"x=1&q=a+b&page=2"),
+            (b"x=1&", b"x=1&q=a+b&page=2"),
+        ];
+        for (output, expected) in cases {
+            let mut buffer = Vec::with_capacity(64);
+            buffer.extend_from_slice(output);
+            serde_structuredqs::append_to_vec(&value, &mut buffer).unwrap();
+            assert_eq!(buffer, expected);
+            assert_eq!(buffer.capacity(), 64);
+        }
+
+        let cases = [
+            ("", "q=a+b&page=2"),
+            ("x=1", "x=1&q=a+b&page=2"),
+            ("x=1&", "x=1&q=a+b&page=2"),
+        ];
+        for (output, expected) in cases {
+            let mut output = String::from(output);
+            serde_structuredqs::append_to_string(&value, &mut output).unwrap();
+            assert_eq!(output, expected);
+        }
+
+        let cases = [
+            ("/search", "/search?q=a+b&page=2"),
+            ("/search?", "/search?q=a+b&page=2"),
+            ("/search?x=1", "/search?x=1&q=a+b&page=2"),
+            ("/search?x=1&", "/search?x=1&q=a+b&page=2"),
+            ("/search#top", "/search?q=a+b&page=2#top"),
+            ("/search?x=1#top", "/search?x=1&q=a+b&page=2#top"),
+        ];
+        for (url, expected) in cases {
+            let mut url = String::from(url);
+            serde_structuredqs::append_to_url(&value, &mut url).unwrap();
+            assert_eq!(url, expected);
+        }
+
+        // Nothing is appended, not even a separator, when there are no pairs.
+        #[derive(Serialize)]
+        struct Optional {
+            page: Option<u32>,
+        }
+        let mut url = String::from("/search#top");
+        serde_structuredqs::append_to_url(&Optional { page: None }, &mut url).unwrap();
+        assert_eq!(url, "/search#top");
+    }
+
+    #[test]
+    fn serialize_into_form_urlencoded_serializer() {
+        #[derive(Serialize)]
+        struct Filter {
+            tags: Vec<String>,
+            page: Page,
+        }
+
+        #[derive(Serialize)]
+        struct Page {
+            number: u32,
+        }
+
+        let value = Filter {
+            tags: vec![String::from("a b"), String::from("c")],
+            page: Page { number: 2 },
+        };
+
+        let mut url = url::Url::parse("https://example.com/search?q=rust#top").unwrap();
+        {
+            let mut pairs = url.query_pairs_mut();
+            serde_structuredqs::to_serializer(&value, &mut pairs).unwrap();
+            pairs.append_pair("lang", "en");
+        }
+        assert_eq!(
+            url.as_str(),
+            "https://example.com/search?q=rust&tags=a+b%2Cc&page.number=2&lang=en#top"
+        );
+
+        let mut serializer = serde_structuredqs::form_urlencoded::Serializer::new(String::new());
+        serde_structuredqs::to_serializer(&value, &mut serializer).unwrap();
+        assert_eq!(
+            serializer.finish(),
+            serde_structuredqs::to_string(&value).unwrap()
+        );
+    }
+
+    #[test]
+    fn serialize_to_pairs() {
+        #[derive(Serialize)]
+        struct Params {
+            q: String,
+            empty: Option<String>,
+            filter: Filter,
+        }
+
+        #[derive(Serialize)]
+        struct Filter {
+            ids: Vec<Option<u32>>,
+            ranges: Vec<(u32, u32)>,
+        }
+
+        let value = Params {
+            q: String::from("a b&c"),
+            empty: None,
+            filter: Filter {
+                ids: vec![Some(1), None],
+                ranges: vec![(1, 2), (3, 4)],
+            },
+        };
+        let pairs = serde_structuredqs::to_pairs(&value).unwrap();
+        assert_eq!(
+            pairs,
+            [
+                ("q", "a b&c"),
+                ("filter.ids", "1,\\0"),
+                ("filter.ranges", "1,2;3,4"),
+            ]
+            .map(|(key, value)| (String::from(key), String::from(value)))
+        );
+
+        // Encoding the pairs gives the same query-string.
+        let encoded = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(pairs)
+            .finish();
+        assert_eq!(encoded, serde_structuredqs::to_string(&value).unwrap());
+    }
+
+    #[test]
+    fn serialize_with_query_builder() {
+        use serde_structuredqs::{Encoding, Error, QueryBuilder, SerializerConfig};
+
+        #[derive(Serialize)]
+        struct Filters {
+            category: String,
+            tags: Vec<String>,
+        }
+
+        #[derive(Serialize)]
+        struct Pagination {
+            page: u32,
+            size: Option<u32>,
+        }
+
+        let filters = Filters {
+            category: String::from("a b"),
+            tags: vec![String::from("x"), String::from("y")],
+        };
+        let pagination = Pagination {
+            page: 2,
+            size: None,
+        };
+
+        let mut builder = QueryBuilder::new(SerializerConfig::new().encoding(Encoding::Rfc3986));
+        builder
+            .append_at("f", &filters)
+            .unwrap()
+            .append(&pagination)
+            .unwrap()
+            .append_at("ids", &vec![1, 2])
+            .unwrap()
+            .append_at("q.lang", &"en")
+            .unwrap()
+            .append_pair("raw.key", "c d")
+            .unwrap();
+        let expected = "f.category=a%20b&f.tags=x%2Cy&page=2&ids=1%2C2&q.lang=en&raw.key=c%20d";
+        assert_eq!(builder.as_str(), expected);
+
+        let duplicates = [
+            ("page", "1"),
+            ("f", "1"),
+            ("f.tags", "1"),
+            ("f.category.x", "1"),
+            ("q", "1"),
+        ];
+        for (key, value) in duplicates {
+            assert!(matches!(
+                builder.append_pair(key, value),
+                Err(Error::Duplicate(duplicate)) if duplicate == key
+            ));
+        }
+        // A failed append leaves the query-string as it was.
+        assert!(matches!(
+            builder.append_at("g", &pagination).unwrap().append(&pagination),
+            Err(Error::Duplicate(key)) if key == "page"
+        ));
+        #[derive(Serialize)]
+        struct Unsupported {
+            a: u32,
+            map: std::collections::BTreeMap<u32, u32>,
+        }
+        let unsupported = Unsupported {
+            a: 1,
+            map: [(1, 2)].into(),
+        };
+        assert!(builder.append_at("h", &unsupported).is_err());
+        builder.append_at("h", &pagination).unwrap();
+        assert_eq!(builder.as_str(), format!("{}&g.page=2&h.page=2", expected));
+
+        // Keys that only share a prefix of their name do not conflict.
+        builder.append_pair("pages", "1").unwrap();
+        builder.append_pair("f-tags", "1").unwrap();
+        assert!(builder.finish().ends_with("&pages=1&f-tags=1"));
+    }
+
+    #[test]
+    fn edit_query() {
+        use serde_structuredqs::{Encoding, Error, QueryEditor, SerializerConfig};
+
+        #[derive(Serialize)]
+        struct Range {
+            min: u32,
+            max: Option<u32>,
+        }
+
+        let input =
+            "utm=a%2Bb&filter.category=A&filter.price.min=1&page=2&filter.price.max=9&q=x+y&Z=%7e";
+        let mut editor = QueryEditor::parse(input).unwrap();
+        assert_eq!(editor.to_string(), input);
+        assert_eq!(editor.get::<String>("q").unwrap(), "x y");
+        assert_eq!(editor.get::<Option<u32>>("size").unwrap(), None);
+
+        editor
+            .set("page", &3)
+            .unwrap()
+            .remove("filter.category")
+            .set("filter.price", &Range { min: 10, max: None })
+            .unwrap()
+            .append("tags", &vec!["a b", "c"])
+            .unwrap();
+        assert_eq!(
+            editor.to_string(),
+            "utm=a%2Bb&filter.price.min=10&page=3&q=x+y&Z=%7e&tags=a+b%2Cc"
+        );
+
+        // Setting a nested key replaces the value of its parent, and setting `None` removes the key.
+        editor
+            .set("q.lang", &"en")
+            .unwrap()
+            .set("page", &None::<u32>)
+            .unwrap()
+            .remove("filter")
+            .remove("missing");
+        assert_eq!(editor.to_string(), "utm=a%2Bb&q.lang=en&Z=%7e&tags=a+b%2Cc");
+
+        // Set values use the encoding of the serializer configuration.
+        let mut editor = QueryEditor::new(
+            "a=1&&b=2",
+            Default::default(),
+            SerializerConfig::new().encoding(Encoding::Rfc3986),
+        )
+        .unwrap();
+        editor.set("a", &"x y").unwrap();
+        assert_eq!(editor.to_string(), "a=x%20y&b=2");
+
+        // A path without any segment is not the whole query.
+        let mut editor = QueryEditor::parse("a=1&b.c=2").unwrap();
+        editor.remove("").remove(".");
+        assert_eq!(editor.to_string(), "a=1&b.c=2");
+        for path in ["", "."] {
+            assert!(matches!(editor.set(path, &1), Err(Error::Custom(_))));
+            assert!(matches!(editor.append(path, &1), Err(Error::Custom(_))));
+        }
+        assert_eq!(editor.to_string(), "a=1&b.c=2");
+
+        assert!(matches!(
+            QueryEditor::parse("a=1&%FF=1"),
+            Err(Error::Parse(_, 4))
+        ));
+    }
+
+    #[test]
+    fn serialize_canonically() {
+        use serde_structuredqs::{DeserializerConfig, Encoding, Error, SerializerConfig};
+
+        #[derive(Serialize)]
+        struct Params {
+            tags: Vec<String>,
+            page: Page,
+            page_size: u32,
+            q: String,
+        }
+
+        #[derive(Serialize)]
+        struct Page {
+            size: u32,
+            number: u32,
+        }
+
+        let params = Params {
+            tags: vec![String::from("a b"), String::from("~")],
+            page: Page {
+                size: 20,
+                number: 2,
+            },
+            page_size: 10,
+            q: String::from("é"),
+        };
+
+        let config = SerializerConfig::new().canonical(true);
+        let canonical = config.to_string(&params).unwrap();
+        // `page` sorts before `page_size` segment by segment, although `.` sorts after `_` as a character.
+        assert_eq!(
+            canonical,
+            "page.number=2&page.size=20&page_size=10&q=%C3%A9&tags=a+b%2C%7E"
+        );
+        assert_eq!(
+            config.to_pairs(&params).unwrap()[..2],
+            [
+                (String::from("page.number"), String::from("2")),
+                (String::from("page.size"), String::from("20")),
+            ]
+        );
+
+        // Every spelling of the same pairs is rewritten into the same string.
+        for input in [
+            serde_structuredqs::to_string(&params).unwrap(),
+            SerializerConfig::new()
+                .encoding(Encoding::Readable)
+                .to_string(&params)
+                .unwrap(),
+            String::from("tags=a%20b,%7e&q=%c3%a9&&page_size=10&page..size=20&page.number=2"),
+        ] {
+            assert_eq!(serde_structuredqs::canonicalize(&input).unwrap(), canonical);
+        }
+
+        let config = config.encoding(Encoding::Rfc3986);
+        assert_eq!(
+            config.canonicalize(&canonical).unwrap(),
+            config.to_string(&params).unwrap()
+        );
+
+        // A key without value is written with an empty value, and pairs of equal keys keep their order.
+        assert_eq!(serde_structuredqs::canonicalize("b&a=").unwrap(), "a=&b=");
+        assert_eq!(
+            serde_structuredqs::canonicalize("b=2&a=3&b=1").unwrap(),
+            "a=3&b=2&b=1"
+        );
+        // A `.` inside a key segment stays inside it.
+        let canonical = serde_structuredqs::canonicalize("a%2Eb=1&a.c=2&a%2ec=3").unwrap();
+        assert_eq!(canonical, "a.c=2&a%2Eb=1&a%2Ec=3");
+        assert_eq!(
+            serde_structuredqs::canonicalize(&canonical).unwrap(),
+            canonical
+        );
+        assert!(serde_structuredqs::canonicalize("%FF=1").is_err());
+
+        // Keys may have any number of segments.
+        let deep = vec!["a"; 40].join(".") + "=1";
+        assert_eq!(serde_structuredqs::canonicalize(&deep).unwrap(), deep);
+
+        // The input is parsed with the given configuration, including its limits.
+        let de_config = DeserializerConfig::new().plus_as_space(false);
+        assert_eq!(
+            SerializerConfig::new()
+                .canonicalize_with(&de_config, "b=1+1&a=x%20y")
+                .unwrap(),
+            "a=x+y&b=1%2B1"
+        );
+        assert!(matches!(
+            SerializerConfig::new().canonicalize_with(&DeserializerConfig::new(), &deep),
+            Err(Error::LimitExceeded(..))
+        ));
+    }
 }